@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Association measures.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AssociationMeasure {
+    /// Pointwise mutual information (base 2).
+    Pmi,
+
+    /// Positive pointwise mutual information, PMI clipped at 0.
+    PositivePmi,
+
+    /// Log-likelihood ratio (G²).
+    LogLikelihood,
+
+    /// t-score.
+    TScore,
+
+    /// Dice coefficient.
+    Dice,
+}
+
+impl AssociationMeasure {
+    /// All association measures.
+    pub fn all() -> &'static [AssociationMeasure] {
+        &[
+            AssociationMeasure::Pmi,
+            AssociationMeasure::PositivePmi,
+            AssociationMeasure::LogLikelihood,
+            AssociationMeasure::TScore,
+            AssociationMeasure::Dice,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AssociationMeasure::Pmi => "pmi",
+            AssociationMeasure::PositivePmi => "ppmi",
+            AssociationMeasure::LogLikelihood => "llr",
+            AssociationMeasure::TScore => "t",
+            AssociationMeasure::Dice => "dice",
+        }
+    }
+}
+
+impl fmt::Display for AssociationMeasure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for AssociationMeasure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AssociationMeasure::all()
+            .iter()
+            .cloned()
+            .find(|measure| measure.name() == s)
+            .ok_or_else(|| format!("unknown association measure: {}", s))
+    }
+}
+
+/// The counts of a head-dependent pair and its marginals.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ContingencyCounts {
+    /// Frequency of the pair.
+    pub joint: u64,
+
+    /// Frequency of the head.
+    pub head: u64,
+
+    /// Frequency of the dependent.
+    pub dependent: u64,
+
+    /// Total frequency of all pairs.
+    pub total: u64,
+}
+
+impl ContingencyCounts {
+    /// The expected frequency of the pair under independence.
+    pub fn expected(&self) -> f64 {
+        self.head as f64 * self.dependent as f64 / self.total as f64
+    }
+
+    /// Compute an association measure.
+    pub fn score(&self, measure: AssociationMeasure) -> f64 {
+        let joint = self.joint as f64;
+
+        match measure {
+            AssociationMeasure::Pmi => (joint / self.expected()).log2(),
+            AssociationMeasure::PositivePmi => (joint / self.expected()).log2().max(0.0),
+            AssociationMeasure::LogLikelihood => self.log_likelihood(),
+            AssociationMeasure::TScore => (joint - self.expected()) / joint.sqrt(),
+            AssociationMeasure::Dice => 2.0 * joint / (self.head + self.dependent) as f64,
+        }
+    }
+
+    fn log_likelihood(&self) -> f64 {
+        let n = self.total as f64;
+        let r1 = self.head as f64;
+        let r2 = n - r1;
+        let c1 = self.dependent as f64;
+        let c2 = n - c1;
+
+        let o11 = self.joint as f64;
+        let o12 = r1 - o11;
+        let o21 = c1 - o11;
+        let o22 = n - r1 - c1 + o11;
+
+        2.0 * (g_term(o11, r1 * c1 / n)
+            + g_term(o12, r1 * c2 / n)
+            + g_term(o21, r2 * c1 / n)
+            + g_term(o22, r2 * c2 / n))
+    }
+}
+
+/// A term of the G² statistic, where 0 ln 0 = 0.
+fn g_term(observed: f64, expected: f64) -> f64 {
+    if observed > 0.0 {
+        observed * (observed / expected).ln()
+    } else {
+        0.0
+    }
+}
+
+/// An association between a head and a dependent in a relation.
+#[derive(Clone, Debug)]
+pub struct Association {
+    pub relation: String,
+    pub head: String,
+    pub dependent: String,
+    pub counts: ContingencyCounts,
+}
+
+/// Counts of head-dependent pairs, grouped by relation.
+///
+/// Marginals are computed per relation: the frequency of a head is its
+/// frequency as the head of the relation, the frequency of a dependent
+/// its frequency as the dependent of the relation, and the total is the
+/// frequency of the relation.
+#[derive(Default)]
+pub struct AssociationTable {
+    relations: HashMap<String, RelationCounts>,
+}
+
+#[derive(Default)]
+struct RelationCounts {
+    pairs: HashMap<(String, String), u64>,
+    heads: HashMap<String, u64>,
+    dependents: HashMap<String, u64>,
+    total: u64,
+}
+
+impl AssociationTable {
+    pub fn new() -> Self {
+        AssociationTable::default()
+    }
+
+    /// Add `count` occurrences of a head-dependent pair.
+    pub fn add(&mut self, relation: &str, head: &str, dependent: &str, count: u64) {
+        let relation_counts = self
+            .relations
+            .entry(relation.to_owned())
+            .or_default();
+
+        *relation_counts
+            .pairs
+            .entry((head.to_owned(), dependent.to_owned()))
+            .or_insert(0) += count;
+        *relation_counts.heads.entry(head.to_owned()).or_insert(0) += count;
+        *relation_counts
+            .dependents
+            .entry(dependent.to_owned())
+            .or_insert(0) += count;
+        relation_counts.total += count;
+    }
+
+    /// Get the associations of all pairs that occur at least `min_freq`
+    /// times.
+    pub fn associations(&self, min_freq: u64) -> Vec<Association> {
+        let mut associations = Vec::new();
+
+        for (relation, relation_counts) in &self.relations {
+            for ((head, dependent), &joint) in &relation_counts.pairs {
+                if joint < min_freq {
+                    continue;
+                }
+
+                associations.push(Association {
+                    relation: relation.clone(),
+                    head: head.clone(),
+                    dependent: dependent.clone(),
+                    counts: ContingencyCounts {
+                        joint,
+                        head: relation_counts.heads[head],
+                        dependent: relation_counts.dependents[dependent],
+                        total: relation_counts.total,
+                    },
+                });
+            }
+        }
+
+        associations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AssociationMeasure, AssociationTable, ContingencyCounts};
+
+    // The 2x2 contingency table of the pair:
+    //
+    //              dep    ¬dep
+    //     head      10      10  |  20
+    //     ¬head     30      50  |  80
+    //               40      60  | 100
+    const COUNTS: ContingencyCounts = ContingencyCounts {
+        joint: 10,
+        head: 20,
+        dependent: 40,
+        total: 100,
+    };
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!(
+            (value - expected).abs() < 1e-9,
+            "{} is not close to {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn measures_of_a_contingency_table() {
+        assert_close(COUNTS.expected(), 8.0);
+
+        // log2(10 / 8)
+        assert_close(COUNTS.score(AssociationMeasure::Pmi), 0.321928094887362);
+        assert_close(COUNTS.score(AssociationMeasure::PositivePmi), 0.321928094887362);
+
+        // 2 (10 ln(10/8) + 10 ln(10/12) + 30 ln(30/32) + 50 ln(50/48))
+        assert_close(COUNTS.score(AssociationMeasure::LogLikelihood), 1.026328074176353);
+
+        // (10 - 8) / sqrt(10)
+        assert_close(COUNTS.score(AssociationMeasure::TScore), 0.632455532033676);
+
+        // 2 * 10 / (20 + 40)
+        assert_close(COUNTS.score(AssociationMeasure::Dice), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn negative_association() {
+        let counts = ContingencyCounts { joint: 2, ..COUNTS };
+
+        // log2(2 / 8)
+        assert_close(counts.score(AssociationMeasure::Pmi), -2.0);
+        assert_close(counts.score(AssociationMeasure::PositivePmi), 0.0);
+
+        // G² does not distinguish positive and negative association.
+        // 2 (2 ln(2/8) + 18 ln(18/12) + 38 ln(38/32) + 42 ln(42/48))
+        assert_close(counts.score(AssociationMeasure::LogLikelihood), 10.895548993380556);
+
+        assert_close(counts.score(AssociationMeasure::TScore), -6.0 / 2f64.sqrt());
+    }
+
+    #[test]
+    fn measure_names() {
+        for &measure in AssociationMeasure::all() {
+            assert_eq!(measure.name().parse(), Ok(measure));
+        }
+        assert!("mi".parse::<AssociationMeasure>().is_err());
+    }
+
+    #[test]
+    fn marginals_are_per_relation() {
+        let mut table = AssociationTable::new();
+        table.add("OBJA", "essen", "Brot", 3);
+        table.add("OBJA", "essen", "Apfel", 1);
+        table.add("OBJA", "backen", "Brot", 2);
+        table.add("SUBJ", "essen", "Peter", 5);
+        table.add("SUBJ", "Brot", "Peter", 7);
+
+        let mut associations = table.associations(2);
+        associations.sort_by(|a, b| {
+            (&a.relation, &a.head, &a.dependent).cmp(&(&b.relation, &b.head, &b.dependent))
+        });
+
+        let pairs: Vec<_> = associations
+            .iter()
+            .map(|a| (a.relation.as_str(), a.head.as_str(), a.dependent.as_str(), a.counts))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (
+                    "OBJA",
+                    "backen",
+                    "Brot",
+                    ContingencyCounts {
+                        joint: 2,
+                        head: 2,
+                        dependent: 5,
+                        total: 6,
+                    }
+                ),
+                (
+                    "OBJA",
+                    "essen",
+                    "Brot",
+                    ContingencyCounts {
+                        joint: 3,
+                        head: 4,
+                        dependent: 5,
+                        total: 6,
+                    }
+                ),
+                (
+                    "SUBJ",
+                    "Brot",
+                    "Peter",
+                    ContingencyCounts {
+                        joint: 7,
+                        head: 7,
+                        dependent: 12,
+                        total: 12,
+                    }
+                ),
+                (
+                    "SUBJ",
+                    "essen",
+                    "Peter",
+                    ContingencyCounts {
+                        joint: 5,
+                        head: 5,
+                        dependent: 12,
+                        total: 12,
+                    }
+                ),
+            ]
+        );
+    }
+}
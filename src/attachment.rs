@@ -0,0 +1,547 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::EdgeDirection;
+use toml;
+
+use clause::segment_clauses;
+use error::{Error, Result};
+use graph::{ancestor_tokens, first_matching_edge, DependencyEdge, DependencyGraph};
+use instance::{InstanceCandidate, InstanceToken, PpInstance};
+use scheme::{contains_relation, strings, AnnotationScheme};
+use topo::feature_value;
+
+/// The names of the built-in attachment rules.
+pub static ATTACHMENT_PRESETS: &[&str] = &["rc", "genitive", "adv", "coordination"];
+
+/// Rules for finding the candidate heads of an attachment.
+///
+/// Rules can be read from TOML files, fields that are not specified in a
+/// file get the value of the relative clause rules:
+///
+/// ```toml
+/// relations = ["REL"]
+/// candidate_tags = ["NN", "NE", "PDS", "PIS", "PPER"]
+/// direction = "preceding"
+/// scope = "clause"
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentRules {
+    /// Relations of the dependents with an ambiguous attachment.
+    pub relations: Vec<String>,
+
+    /// Relation of the object of the dependent, such as the second
+    /// conjunct of a conjunction. Dependents without an object have `_`
+    /// as their object.
+    pub object_relations: Vec<String>,
+
+    /// Prefixes of the tags of candidate heads. Tags are read from the
+    /// column of the annotation scheme (see `AnnotationScheme::tag`).
+    pub candidate_tags: Vec<String>,
+
+    /// Candidates have the same main category (the first character of
+    /// the tag) as the object, or the dependent if it has no object.
+    pub same_category: bool,
+
+    /// The side of the dependent where candidates are searched.
+    pub direction: SearchDirection,
+
+    /// The part of the sentence where candidates are searched.
+    pub scope: Scope,
+
+    /// The maximum distance of candidates to the dependent.
+    pub max_distance: Option<usize>,
+}
+
+impl Default for AttachmentRules {
+    fn default() -> Self {
+        AttachmentRules {
+            relations: strings(&["REL"]),
+            object_relations: Vec::new(),
+            candidate_tags: strings(&["NN", "NE", "PDS", "PIS", "PPER"]),
+            same_category: false,
+            direction: SearchDirection::Preceding,
+            scope: Scope::Clause,
+            max_distance: None,
+        }
+    }
+}
+
+/// The side of a dependent where candidate heads are searched.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SearchDirection {
+    Preceding,
+    Following,
+    Both,
+}
+
+/// The part of a sentence where candidate heads are searched.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// The clause of the dependent (see `segment_clauses`). If the
+    /// dependent is the finite verb of a clause, such as a relative
+    /// clause, this is the clause that it is embedded in.
+    Clause,
+
+    /// The whole sentence.
+    Sentence,
+}
+
+impl AttachmentRules {
+    /// Get built-in attachment rules for the TüBa-D/Z scheme.
+    ///
+    /// The rules use TüBa-D/Z relations and STTS tags, they do not find
+    /// any attachments in corpora with other annotation schemes.
+    ///
+    /// Returns `None` if there are no rules with the given name, see
+    /// `ATTACHMENT_PRESETS` for the available rules.
+    pub fn preset(name: &str) -> Option<AttachmentRules> {
+        match name {
+            "rc" => Some(AttachmentRules::default()),
+            "genitive" => Some(AttachmentRules {
+                relations: strings(&["GMOD"]),
+                candidate_tags: strings(&["NN", "NE"]),
+                ..AttachmentRules::default()
+            }),
+            "adv" => Some(AttachmentRules {
+                relations: strings(&["ADV"]),
+                candidate_tags: strings(&["V", "ADJ", "ADV"]),
+                direction: SearchDirection::Both,
+                ..AttachmentRules::default()
+            }),
+            "coordination" => Some(AttachmentRules {
+                relations: strings(&["KON"]),
+                object_relations: strings(&["CJ"]),
+                candidate_tags: strings(&["N", "V", "ADJ", "ADV", "P", "CARD"]),
+                same_category: true,
+                scope: Scope::Sentence,
+                ..AttachmentRules::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Read attachment rules from a TOML file.
+    pub fn from_file<P>(path: P) -> Result<AttachmentRules>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", path.to_string_lossy(), e))
+        })?;
+        AttachmentRules::from_toml(&data)
+    }
+
+    /// Parse attachment rules from TOML.
+    pub fn from_toml(data: &str) -> Result<AttachmentRules> {
+        toml::from_str(data).map_err(Error::Rules)
+    }
+
+    /// Read attachment rules from a file, or get built-in rules if there
+    /// is no file with the given name.
+    pub fn preset_or_file(name: &str) -> Result<AttachmentRules> {
+        if Path::new(name).is_file() {
+            return AttachmentRules::from_file(name);
+        }
+
+        match AttachmentRules::preset(name) {
+            Some(rules) => Ok(rules),
+            None => AttachmentRules::from_file(name),
+        }
+    }
+
+    fn is_candidate_tag(&self, tag: &str) -> bool {
+        self.candidate_tags.iter().any(|p| tag.starts_with(p.as_str()))
+    }
+
+    fn in_direction(&self, dependent: NodeIndex, idx: NodeIndex) -> bool {
+        match self.direction {
+            SearchDirection::Preceding => idx < dependent,
+            SearchDirection::Following => idx > dependent,
+            SearchDirection::Both => idx != dependent,
+        }
+    }
+
+    fn in_distance(&self, dependent: NodeIndex, idx: NodeIndex) -> bool {
+        match self.max_distance {
+            Some(max_distance) => {
+                (idx.index() as isize - dependent.index() as isize).unsigned_abs() <= max_distance
+            }
+            None => true,
+        }
+    }
+}
+
+/// A dependent with its candidate heads.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttachmentAmbiguity {
+    pub dependent: NodeIndex,
+    pub object: Option<NodeIndex>,
+
+    /// The candidate heads, in sentence order.
+    pub candidates: Vec<NodeIndex>,
+
+    /// The head of the dependent.
+    pub head: NodeIndex,
+}
+
+impl AttachmentAmbiguity {
+    /// Convert to an instance in the format of `extract-ambiguous-pps`,
+    /// where the preposition columns hold the dependent.
+    pub fn to_instance(
+        &self,
+        graph: &DependencyGraph,
+        scheme: &AnnotationScheme,
+        sent_id: &str,
+        lemma: bool,
+    ) -> PpInstance {
+        let token = |idx: NodeIndex| {
+            let token = graph[idx].token;
+            let form = if lemma { token.lemma() } else { token.form() };
+            InstanceToken {
+                form: form.unwrap_or("_").to_owned(),
+                pos: scheme.tag(token).unwrap_or("_").to_owned(),
+                field: feature_value(token, &scheme.topological_fields.feature)
+                    .unwrap_or_else(|| "_".to_owned()),
+            }
+        };
+
+        let dependent = self.dependent.index() as isize;
+
+        let candidates = self
+            .candidates
+            .iter()
+            .map(|&idx| {
+                let side = self
+                    .candidates
+                    .iter()
+                    .filter(|&&other| (other < self.dependent) == (idx < self.dependent));
+                let rank = if idx < self.dependent {
+                    -(side.filter(|&&other| other >= idx).count() as isize)
+                } else {
+                    side.filter(|&&other| other <= idx).count() as isize
+                };
+
+                InstanceCandidate {
+                    token: token(idx),
+                    distance: idx.index() as isize - dependent,
+                    rank,
+                    head: idx == self.head,
+                    score: None,
+                }
+            })
+            .collect();
+
+        PpInstance {
+            source: None,
+            sent_id: sent_id.to_owned(),
+            position: Some(self.dependent.index() + 1),
+            preposition: token(self.dependent),
+            object: match self.object {
+                Some(object) => token(object),
+                None => InstanceToken {
+                    form: "_".to_owned(),
+                    pos: "_".to_owned(),
+                    field: "_".to_owned(),
+                },
+            },
+            candidates,
+        }
+    }
+}
+
+/// Find the dependents of the given relations with their candidate heads.
+///
+/// Only dependents whose head is one of the candidates are returned.
+/// Clause scope requires topological fields, see `segment_clauses`.
+pub fn attachment_ambiguities(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    rules: &AttachmentRules,
+) -> Vec<AttachmentAmbiguity> {
+    let clauses = match rules.scope {
+        Scope::Clause => Some(segment_clauses(graph, scheme)),
+        Scope::Sentence => None,
+    };
+
+    let mut ambiguities = Vec::new();
+
+    for edge in graph.edge_references() {
+        match *edge.weight() {
+            DependencyEdge::Relation(Some(relation))
+                if contains_relation(&rules.relations, relation) => {}
+            _ => continue,
+        }
+
+        let dependent = edge.target();
+        let head = edge.source();
+
+        let object = first_matching_edge(graph, dependent, EdgeDirection::Outgoing, |e| match *e {
+            DependencyEdge::Relation(Some(relation)) => {
+                contains_relation(&rules.object_relations, relation)
+            }
+            _ => false,
+        });
+
+        let category = scheme
+            .tag(graph[object.unwrap_or(dependent)].token)
+            .and_then(|pos| pos.chars().next());
+
+        let scope = match clauses {
+            Some(ref clauses) => {
+                let clause = ok_or_continue!(clauses.clause_of(dependent));
+                if clauses.clause(clause).finite_verb == dependent {
+                    Some(ok_or_continue!(clauses.clause(clause).parent))
+                } else {
+                    Some(clause)
+                }
+            }
+            None => None,
+        };
+
+        let candidates: Vec<_> = graph
+            .node_indices()
+            .filter(|&idx| !graph[idx].is_root() && Some(idx) != object)
+            .filter(|&idx| rules.in_direction(dependent, idx) && rules.in_distance(dependent, idx))
+            .filter(|&idx| {
+                let pos = match scheme.tag(graph[idx].token) {
+                    Some(pos) => pos,
+                    None => return false,
+                };
+
+                rules.is_candidate_tag(pos) &&
+                    (!rules.same_category || pos.chars().next() == category)
+            })
+            .filter(|&idx| match (scope, clauses.as_ref()) {
+                (Some(scope), Some(clauses)) => clauses.clause_of(idx) == Some(scope),
+                _ => true,
+            })
+            .filter(|&idx| !dominates(graph, dependent, idx))
+            .collect();
+
+        if !candidates.contains(&head) {
+            continue;
+        }
+
+        ambiguities.push(AttachmentAmbiguity {
+            dependent,
+            object,
+            candidates,
+            head,
+        });
+    }
+
+    ambiguities.sort_by_key(|ambiguity| ambiguity.dependent);
+
+    ambiguities
+}
+
+/// Returns `true` if `ancestor` dominates `idx`.
+fn dominates(graph: &DependencyGraph, ancestor: NodeIndex, idx: NodeIndex) -> bool {
+    ancestor_tokens(graph, idx)
+        .take(graph.node_count())
+        .any(|idx| idx == ancestor)
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::{attachment_ambiguities, AttachmentAmbiguity, AttachmentRules, Scope};
+    use graph::sentence_to_graph;
+    use scheme::{strings, AnnotationScheme};
+    use test_util::sentence;
+
+    fn ambiguity(
+        dependent: usize,
+        object: Option<usize>,
+        candidates: &[usize],
+        head: usize,
+    ) -> AttachmentAmbiguity {
+        AttachmentAmbiguity {
+            dependent: NodeIndex::new(dependent),
+            object: object.map(NodeIndex::new),
+            candidates: candidates.iter().cloned().map(NodeIndex::new).collect(),
+            head: NodeIndex::new(head),
+        }
+    }
+
+    #[test]
+    fn relative_clauses() {
+        let sentence = sentence(
+            "1   Er     er     PPER   PPER   tf:VF  2   SUBJ     _  _
+             2   sieht  sehen  VVFIN  VVFIN  tf:LK  0   ROOT     _  _
+             3   den    der    ART    ART    tf:MF  4   DET      _  _
+             4   Mann   Mann   NN     NN     tf:MF  2   OBJA     _  _
+             5   mit    mit    APPR   APPR   tf:MF  4   PP       _  _
+             6   dem    der    ART    ART    tf:MF  7   DET      _  _
+             7   Hund   Hund   NN     NN     tf:MF  5   PN       _  _
+             8   ,      ,      $,     $,     _      7   -PUNCT-  _  _
+             9   der    der    PRELS  PRELS  tf:C   10  SUBJ     _  _
+             10  bellt  bellen VVFIN  VVFIN  tf:VC  7   REL      _  _
+             11  .      .      $.     $.     _      10  -PUNCT-  _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let scheme = AnnotationScheme::default();
+        let rules = AttachmentRules::preset("rc").unwrap();
+
+        // The relative clause is attached in the clause that it is
+        // embedded in.
+        let ambiguities = attachment_ambiguities(&graph, &scheme, &rules);
+        assert_eq!(ambiguities, vec![ambiguity(9, None, &[0, 3, 6], 6)]);
+
+        let instance = ambiguities[0].to_instance(&graph, &scheme, "s1", false);
+        assert_eq!(instance.sent_id, "s1");
+        assert_eq!(instance.position, Some(10));
+        assert_eq!(instance.preposition.form, "bellt");
+        assert_eq!(instance.preposition.pos, "VVFIN");
+        assert_eq!(instance.preposition.field, "VC");
+        assert_eq!(instance.object.form, "_");
+        assert_eq!(
+            instance
+                .candidates
+                .iter()
+                .map(|c| (c.token.form.as_str(), c.distance, c.rank, c.head))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Er", -9, -3, false),
+                ("Mann", -6, -2, false),
+                ("Hund", -3, -1, true),
+            ]
+        );
+
+        let lemmas = ambiguities[0].to_instance(&graph, &scheme, "s1", true);
+        assert_eq!(lemmas.preposition.form, "bellen");
+
+        // Dependents whose head is not a candidate are skipped.
+        let rules = AttachmentRules {
+            candidate_tags: strings(&["PPER"]),
+            ..rules
+        };
+        assert!(attachment_ambiguities(&graph, &scheme, &rules).is_empty());
+
+        let rules = AttachmentRules {
+            max_distance: Some(3),
+            ..AttachmentRules::preset("rc").unwrap()
+        };
+        assert_eq!(
+            attachment_ambiguities(&graph, &scheme, &rules),
+            vec![ambiguity(9, None, &[6], 6)]
+        );
+    }
+
+    #[test]
+    fn genitives_in_clause_scope() {
+        let sentence = sentence(
+            "1   Der     der     ART    ART    tf:VF  2   DET      _  _
+             2   Chef    Chef    NN     NN     tf:VF  3   SUBJ     _  _
+             3   sagt    sagen   VVFIN  VVFIN  tf:LK  0   ROOT     _  _
+             4   ,       ,       $,     $,     _      3   -PUNCT-  _  _
+             5   dass    dass    KOUS   KOUS   tf:C   10  KONJ     _  _
+             6   das     der     ART    ART    tf:MF  7   DET      _  _
+             7   Haus    Haus    NN     NN     tf:MF  10  SUBJ     _  _
+             8   des     der     ART    ART    tf:MF  9   DET      _  _
+             9   Mannes  Mann    NN     NN     tf:MF  7   GMOD     _  _
+             10  brennt  brennen VVFIN  VVFIN  tf:VC  3   OBJC     _  _
+             11  .       .       $.     $.     _      10  -PUNCT-  _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let scheme = AnnotationScheme::default();
+        let rules = AttachmentRules::preset("genitive").unwrap();
+
+        // The noun of the main clause is not a candidate.
+        let ambiguities = attachment_ambiguities(&graph, &scheme, &rules);
+        assert_eq!(ambiguities, vec![ambiguity(8, None, &[6], 6)]);
+
+        let instance = ambiguities[0].to_instance(&graph, &scheme, "s1", false);
+        assert_eq!(instance.candidates.len(), 1);
+        assert_eq!(instance.candidates[0].distance, -2);
+        assert_eq!(instance.candidates[0].rank, -1);
+        assert!(instance.candidates[0].head);
+
+        let rules = AttachmentRules {
+            scope: Scope::Sentence,
+            ..rules
+        };
+        assert_eq!(
+            attachment_ambiguities(&graph, &scheme, &rules),
+            vec![ambiguity(8, None, &[1, 6], 6)]
+        );
+    }
+
+    #[test]
+    fn adverbials() {
+        let sentence = sentence(
+            "1  Er          er         PPER   PPER   tf:VF  2  SUBJ     _  _
+             2  hat         haben      VAFIN  VAFIN  tf:LK  0  ROOT     _  _
+             3  gestern     gestern    ADV    ADV    tf:MF  5  ADV      _  _
+             4  schnell     schnell    ADJD   ADJD   tf:MF  5  ADV      _  _
+             5  gearbeitet  arbeiten   VVPP   VVPP   tf:VC  2  AUX      _  _
+             6  .           .          $.     $.     _      5  -PUNCT-  _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let scheme = AnnotationScheme::default();
+        let rules = AttachmentRules::preset("adv").unwrap();
+
+        let ambiguities = attachment_ambiguities(&graph, &scheme, &rules);
+        assert_eq!(
+            ambiguities,
+            vec![
+                ambiguity(2, None, &[1, 3, 4], 4),
+                ambiguity(3, None, &[1, 2, 4], 4),
+            ]
+        );
+
+        // Candidates on both sides are ranked from the dependent.
+        let instance = ambiguities[0].to_instance(&graph, &scheme, "s1", false);
+        assert_eq!(
+            instance
+                .candidates
+                .iter()
+                .map(|c| (c.distance, c.rank))
+                .collect::<Vec<_>>(),
+            vec![(-1, -1), (1, 1), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn coordinations() {
+        let sentence = sentence(
+            "1  Er      er      PPER   PPER   tf:VF  2  SUBJ     _  _
+             2  gibt    geben   VVFIN  VVFIN  tf:LK  0  ROOT     _  _
+             3  dem     der     ART    ART    tf:MF  4  DET      _  _
+             4  Kind    Kind    NN     NN     tf:MF  2  OBJD     _  _
+             5  Äpfel   Apfel   NN     NN     tf:MF  2  OBJA     _  _
+             6  und     und     KON    KON    tf:MF  5  KON      _  _
+             7  Birnen  Birne   NN     NN     tf:MF  6  CJ       _  _
+             8  .       .       $.     $.     _      7  -PUNCT-  _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let scheme = AnnotationScheme::default();
+        let rules = AttachmentRules::preset("coordination").unwrap();
+
+        // The pronoun is not in the category of the second conjunct.
+        let ambiguities = attachment_ambiguities(&graph, &scheme, &rules);
+        assert_eq!(ambiguities, vec![ambiguity(5, Some(6), &[3, 4], 4)]);
+
+        let instance = ambiguities[0].to_instance(&graph, &scheme, "s1", false);
+        assert_eq!(instance.preposition.form, "und");
+        assert_eq!(instance.object.form, "Birnen");
+        assert_eq!(instance.object.pos, "NN");
+        assert_eq!(
+            instance
+                .candidates
+                .iter()
+                .map(|c| (c.distance, c.rank, c.head))
+                .collect::<Vec<_>>(),
+            vec![(-2, -2, false), (-1, -1, true)]
+        );
+    }
+}
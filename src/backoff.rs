@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, Write};
+
+use error::Result;
+
+/// A candidate attachment of a PP.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AttachmentTuple<'a> {
+    pub head: &'a str,
+    pub head_pos: &'a str,
+    pub preposition: &'a str,
+    pub object: &'a str,
+}
+
+/// How often a tuple was seen as a candidate and how often it was
+/// attached.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AttachmentCounts {
+    pub attached: u64,
+    pub total: u64,
+}
+
+/// Count tables of the back-off model. The tables starting with `u`
+/// count unambiguous attachments.
+const TABLES: &[&str] = &["hpo", "hp", "tpo", "tp", "p", "uhp", "uh", "utp", "ut"];
+
+/// The table with preposition counts, every candidate is counted once
+/// in this table.
+const PREPOSITION_TABLE: usize = 4;
+
+/// The first table of unambiguous attachment counts.
+const UNAMBIGUOUS_TABLE: usize = 5;
+
+/// The tables that are used at each back-off level.
+const LEVELS: &[&[usize]] = &[&[0], &[1, 2], &[3], &[4]];
+
+/// The number of back-off levels that use the head form.
+const LEXICAL_LEVELS: usize = 2;
+
+/// Backed-off PP attachment model (Collins & Brooks, 1995).
+///
+/// The model estimates the probability that a candidate head is the
+/// head of a PP. Rather than choosing between a verb and a noun, each
+/// candidate is scored separately, using the head form (h), head tag (t),
+/// preposition (p), and prepositional object (o). The estimate is the
+/// relative frequency of attachment at the first level with evidence:
+///
+/// 1. (h, t, p, o)
+/// 2. (h, t, p) and (t, p, o)
+/// 3. the estimate of the following levels, adjusted by the lexical
+///    association of the head and the preposition in unambiguous
+///    attachments (see `add_unambiguous`)
+/// 4. (t, p)
+/// 5. (p)
+///
+/// If the preposition was not seen, the estimate is the relative
+/// frequency of attachment over all candidates (level 6).
+#[derive(Default)]
+pub struct BackoffModel {
+    tables: [HashMap<String, AttachmentCounts>; 9],
+    prior: AttachmentCounts,
+}
+
+impl BackoffModel {
+    pub fn new() -> Self {
+        BackoffModel::default()
+    }
+
+    /// Read a model in the format written by `write`.
+    pub fn read(reader: &mut dyn BufRead) -> Result<Self> {
+        let mut model = BackoffModel::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let mut parts = line.splitn(4, '\t');
+
+            let table = parts
+                .next()
+                .and_then(|table| TABLES.iter().position(|&name| name == table));
+            let attached = parts.next().and_then(|count| count.parse().ok());
+            let total = parts.next().and_then(|count| count.parse().ok());
+            let key = parts.next();
+
+            match (table, attached, total, key) {
+                (Some(table), Some(attached), Some(total), Some(key)) => {
+                    let counts = AttachmentCounts { attached, total };
+                    if table == PREPOSITION_TABLE {
+                        model.prior.attached += attached;
+                        model.prior.total += total;
+                    }
+                    model.tables[table].insert(key.to_owned(), counts);
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("malformed line in attachment model: {}", line),
+                    )
+                    .into())
+                }
+            }
+        }
+
+        Ok(model)
+    }
+
+    /// Count a candidate attachment.
+    pub fn add(&mut self, tuple: &AttachmentTuple, attached: bool) {
+        self.prior.total += 1;
+        if attached {
+            self.prior.attached += 1;
+        }
+
+        for (table, key) in self.tables.iter_mut().zip(keys(tuple)) {
+            let counts = table.entry(key).or_default();
+            counts.total += 1;
+            if attached {
+                counts.attached += 1;
+            }
+        }
+    }
+
+    /// Count an unambiguous attachment, such as a PP without competing
+    /// candidate heads.
+    ///
+    /// Unambiguous attachments do not provide negative evidence, so they
+    /// are not counted as candidates. Instead, they are used to estimate
+    /// the lexical association of a head and a preposition (Hindle &
+    /// Rooth, 1993): the ratio of P(p | h, t) to P(p | t), where
+    /// P(p | h, t) is smoothed towards P(p | t). When there is no
+    /// evidence for the head form in the ambiguous attachments, the odds
+    /// of the estimate are multiplied by the lexical association.
+    pub fn add_unambiguous(&mut self, tuple: &AttachmentTuple) {
+        let tables = self.tables[UNAMBIGUOUS_TABLE..].iter_mut();
+        for (table, key) in tables.zip(unambiguous_keys(tuple)) {
+            let counts = table.entry(key).or_default();
+            counts.attached += 1;
+            counts.total += 1;
+        }
+    }
+
+    /// Estimate the probability that a candidate is attached.
+    ///
+    /// Returns the estimate and the (1-based) back-off level that was
+    /// used. The estimate is `None` if the model was not trained.
+    pub fn score(&self, tuple: &AttachmentTuple) -> Option<(f64, usize)> {
+        let keys = keys(tuple);
+
+        for (level, tables) in LEVELS[..LEXICAL_LEVELS].iter().enumerate() {
+            if let Some(estimate) = self.estimate(&keys, tables) {
+                return Some((estimate, level + 1));
+            }
+        }
+
+        // The levels after the lexical association level.
+        let (estimate, level) = LEVELS[LEXICAL_LEVELS..]
+            .iter()
+            .enumerate()
+            .find_map(|(level, tables)| {
+                self.estimate(&keys, tables)
+                    .map(|estimate| (estimate, LEXICAL_LEVELS + level + 2))
+            })
+            .or_else(|| {
+                self.prior
+                    .estimate()
+                    .map(|estimate| (estimate, LEVELS.len() + 2))
+            })?;
+
+        match self.lexical_association(tuple) {
+            Some(association) => Some((scale_odds(estimate, association), LEXICAL_LEVELS + 1)),
+            None => Some((estimate, level)),
+        }
+    }
+
+    /// The relative frequency of attachment in the given tables.
+    fn estimate(&self, keys: &[String], tables: &[usize]) -> Option<f64> {
+        let mut sum = AttachmentCounts::default();
+        for &table in tables {
+            if let Some(counts) = self.tables[table].get(&keys[table]) {
+                sum.attached += counts.attached;
+                sum.total += counts.total;
+            }
+        }
+
+        sum.estimate()
+    }
+
+    /// The lexical association of the head and the preposition in
+    /// unambiguous attachments. Returns `None` if the head or the tag and
+    /// the preposition were not seen in unambiguous attachments.
+    fn lexical_association(&self, tuple: &AttachmentTuple) -> Option<f64> {
+        let keys = unambiguous_keys(tuple);
+        let count = |table: usize| {
+            self.tables[UNAMBIGUOUS_TABLE + table]
+                .get(&keys[table])
+                .map(|counts| counts.total as f64)
+                .unwrap_or(0.0)
+        };
+
+        let (head_prep, head, tag_prep, tag) = (count(0), count(1), count(2), count(3));
+        if head == 0.0 || tag_prep == 0.0 {
+            return None;
+        }
+
+        let tag_prob = tag_prep / tag;
+        let head_prob = (head_prep + tag_prob) / (head + 1.0);
+
+        Some(head_prob / tag_prob)
+    }
+
+    /// Write the model. Every line contains the table, the attachment
+    /// count, the candidate count, and the key, separated by tabs.
+    pub fn write(&self, writer: &mut dyn Write) -> Result<()> {
+        for (name, table) in TABLES.iter().zip(&self.tables) {
+            let mut entries: Vec<_> = table.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+            for (key, counts) in entries {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    name, counts.attached, counts.total, key
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AttachmentCounts {
+    /// The relative frequency of attachment, `None` if there are no
+    /// candidates.
+    fn estimate(&self) -> Option<f64> {
+        if self.total > 0 {
+            Some(self.attached as f64 / self.total as f64)
+        } else {
+            None
+        }
+    }
+}
+
+/// Multiply the odds of a probability by a factor.
+fn scale_odds(probability: f64, factor: f64) -> f64 {
+    if probability <= 0.0 || probability >= 1.0 {
+        return probability;
+    }
+
+    let odds = probability / (1.0 - probability) * factor;
+    odds / (1.0 + odds)
+}
+
+/// The keys of a tuple in each table.
+fn keys(tuple: &AttachmentTuple) -> Vec<String> {
+    vec![
+        format!(
+            "{} {} {} {}",
+            tuple.head, tuple.head_pos, tuple.preposition, tuple.object
+        ),
+        format!("{} {} {}", tuple.head, tuple.head_pos, tuple.preposition),
+        format!("{} {} {}", tuple.head_pos, tuple.preposition, tuple.object),
+        format!("{} {}", tuple.head_pos, tuple.preposition),
+        tuple.preposition.to_owned(),
+    ]
+}
+
+/// The keys of a tuple in each table of unambiguous attachments.
+fn unambiguous_keys(tuple: &AttachmentTuple) -> Vec<String> {
+    vec![
+        format!("{} {} {}", tuple.head, tuple.head_pos, tuple.preposition),
+        format!("{} {}", tuple.head, tuple.head_pos),
+        format!("{} {}", tuple.head_pos, tuple.preposition),
+        tuple.head_pos.to_owned(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttachmentTuple, BackoffModel};
+
+    fn tuple<'a>(head: &'a str, head_pos: &'a str, preposition: &'a str) -> AttachmentTuple<'a> {
+        AttachmentTuple {
+            head,
+            head_pos,
+            preposition,
+            object: "Messer",
+        }
+    }
+
+    #[test]
+    fn backoff_levels() {
+        let mut model = BackoffModel::new();
+        model.add(&tuple("schneidet", "VVFIN", "mit"), true);
+        model.add(&tuple("Brot", "NN", "mit"), false);
+        model.add(&tuple("Brot", "NN", "ohne"), true);
+        model.add(&tuple("schneidet", "VVFIN", "ohne"), false);
+
+        assert_eq!(model.score(&tuple("schneidet", "VVFIN", "mit")), Some((1.0, 1)));
+        assert_eq!(model.score(&tuple("isst", "VVFIN", "mit")), Some((1.0, 2)));
+
+        // The preposition is the last back-off level.
+        assert_eq!(model.score(&tuple("isst", "ADJA", "mit")), Some((0.5, 5)));
+
+        // Unseen prepositions get the prior.
+        assert_eq!(model.score(&tuple("isst", "VVFIN", "auf")), Some((0.5, 6)));
+    }
+
+    #[test]
+    fn unambiguous_lexical_association() {
+        let mut model = BackoffModel::new();
+        model.add(&tuple("schneidet", "VVFIN", "mit"), true);
+        model.add(&tuple("isst", "VVFIN", "mit"), false);
+
+        for _ in 0..3 {
+            model.add_unambiguous(&tuple("fährt", "VVFIN", "mit"));
+        }
+        model.add_unambiguous(&tuple("fährt", "VVFIN", "nach"));
+        for _ in 0..4 {
+            model.add_unambiguous(&tuple("geht", "VVFIN", "nach"));
+        }
+
+        // Ambiguous evidence for the head is preferred.
+        assert_eq!(model.score(&tuple("isst", "VVFIN", "mit")), Some((0.0, 1)));
+
+        // P(mit | VVFIN) = 3/8, P(mit | fährt) = (3 + 3/8) / (4 + 1),
+        // the odds of the (t, p) estimate 0.5 are multiplied by 1.8.
+        let fahren = AttachmentTuple {
+            object: "Zug",
+            ..tuple("fährt", "VVFIN", "mit")
+        };
+        let (score, level) = model.score(&fahren).unwrap();
+        assert_eq!(level, 3);
+        assert!((score - 1.8 / 2.8).abs() < 1e-9);
+
+        // Heads that were only seen with other prepositions have a low
+        // association: P(mit | geht) = (0 + 3/8) / (4 + 1).
+        let gehen = AttachmentTuple {
+            object: "Zug",
+            ..tuple("geht", "VVFIN", "mit")
+        };
+        let (score, level) = model.score(&gehen).unwrap();
+        assert_eq!(level, 3);
+        assert!((score - 0.2 / 1.2).abs() < 1e-9);
+
+        // Without unambiguous evidence for the head, the model backs off.
+        let sitzen = AttachmentTuple {
+            object: "Zug",
+            ..tuple("sitzt", "VVFIN", "mit")
+        };
+        assert_eq!(model.score(&sitzen), Some((0.5, 4)));
+    }
+
+    #[test]
+    fn read_write_roundtrip() {
+        let mut model = BackoffModel::new();
+        model.add(&tuple("schneidet", "VVFIN", "mit"), true);
+        model.add(&tuple("Brot", "NN", "mit"), false);
+        model.add(&tuple("Brot", "NN", "ohne"), false);
+        model.add_unambiguous(&tuple("isst", "VVFIN", "mit"));
+
+        let mut data = Vec::new();
+        model.write(&mut data).unwrap();
+        let read = BackoffModel::read(&mut &data[..]).unwrap();
+
+        let unseen = tuple("isst", "VVFIN", "auf");
+        assert_eq!(read.score(&unseen), model.score(&unseen));
+        assert_eq!(read.score(&unseen), Some((1.0 / 3.0, 6)));
+
+        let unambiguous = AttachmentTuple {
+            object: "Apfel",
+            ..tuple("isst", "VVFIN", "mit")
+        };
+        assert_eq!(read.score(&unambiguous), model.score(&unambiguous));
+        assert_eq!(read.score(&unambiguous).map(|(_, level)| level), Some(3));
+    }
+}
@@ -0,0 +1,217 @@
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::cmp::Ordering;
+use std::env::args;
+use std::io::{BufRead, Write};
+
+use getopts::Options;
+use syncooc::{inputs_or_stdin, ok_or_continue, or_exit, or_stdout, or_warn, Association,
+              AssociationMeasure, AssociationTable};
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] [INPUT_FILE...]\n\n\
+         Compute association measures from extracted tuples or count tables.\n\
+         Each output line contains the relation (when relation columns are\n\
+         given), head, dependent, frequency, {}.",
+        program,
+        AssociationMeasure::all()
+            .iter()
+            .map(AssociationMeasure::name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+/// The columns of the relation, head, and dependent in a tuple.
+struct TupleColumns {
+    relation: Vec<usize>,
+    head: Vec<usize>,
+    dependent: Vec<usize>,
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag(
+        "c",
+        "counts",
+        "the input is a count table, the first column is the count",
+    );
+    opts.optopt("d", "dependent", "dependent columns (default: 3,4)", "COLS");
+    opts.optopt("H", "head", "head columns (default: 1,2)", "COLS");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt(
+        "m",
+        "min-freq",
+        "only print pairs with at least N occurrences (default: 1)",
+        "N",
+    );
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optopt(
+        "r",
+        "relation",
+        "relation columns, marginals are computed per relation (default: none)",
+        "COLS",
+    );
+    opts.optopt(
+        "s",
+        "sort",
+        "rank by MEASURE: pmi, ppmi, llr, t, or dice (default: llr)",
+        "MEASURE",
+    );
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    let columns = TupleColumns {
+        relation: or_exit(parse_columns(
+            &matches.opt_str("r").unwrap_or_default(),
+        )),
+        head: or_exit(parse_columns(
+            &matches.opt_str("H").unwrap_or_else(|| "1,2".to_owned()),
+        )),
+        dependent: or_exit(parse_columns(
+            &matches.opt_str("d").unwrap_or_else(|| "3,4".to_owned()),
+        )),
+    };
+    let min_freq = or_exit(matches.opt_get_default("m", 1));
+    let measure = or_exit(matches.opt_get_default("s", AssociationMeasure::LogLikelihood));
+    let counts = matches.opt_present("c");
+
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+
+    let mut table = AssociationTable::new();
+    for input in &inputs {
+        let reader = or_exit(input.buf_read());
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = or_exit(line);
+            let (relation, head, dependent, count) = ok_or_continue!(or_warn(
+                parse_line(&line, &columns, counts)
+                    .map_err(|e| format!("{}, line {}: {}", input.name(), line_no + 1, e))
+            ));
+            table.add(&relation, &head, &dependent, count);
+        }
+    }
+
+    let mut associations = table.associations(min_freq);
+    associations.sort_by(|a, b| rank_order(a, b, measure));
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    for association in associations {
+        if !columns.relation.is_empty() {
+            or_exit(write!(writer, "{} ", association.relation));
+        }
+
+        or_exit(write!(
+            writer,
+            "{} {} {}",
+            association.head, association.dependent, association.counts.joint
+        ));
+
+        for &measure in AssociationMeasure::all() {
+            or_exit(write!(writer, " {:.4}", association.counts.score(measure)));
+        }
+
+        or_exit(writeln!(writer));
+    }
+
+    or_exit(writer.finish());
+}
+
+/// Parse a column specification, such as `1,2` or `1-2,5`. Columns are
+/// 1-based.
+fn parse_columns(spec: &str) -> Result<Vec<usize>, String> {
+    let mut columns = Vec::new();
+
+    for part in spec.split(',').filter(|part| !part.is_empty()) {
+        let mut bounds = part.splitn(2, '-');
+        let first = parse_column(bounds.next().unwrap_or(""))?;
+        let last = match bounds.next() {
+            Some(last) => parse_column(last)?,
+            None => first,
+        };
+
+        if last < first {
+            return Err(format!("invalid column range: {}", part));
+        }
+
+        columns.extend(first..=last);
+    }
+
+    Ok(columns)
+}
+
+fn parse_column(column: &str) -> Result<usize, String> {
+    match column.parse() {
+        Ok(column) if column > 0 => Ok(column),
+        _ => Err(format!("invalid column: {}", column)),
+    }
+}
+
+fn parse_line(
+    line: &str,
+    columns: &TupleColumns,
+    counts: bool,
+) -> Result<(String, String, String, u64), String> {
+    let mut fields: Vec<_> = line.split_whitespace().collect();
+
+    let count = if counts {
+        if fields.is_empty() {
+            return Err("missing count".to_owned());
+        }
+
+        let count = fields.remove(0);
+        count
+            .parse()
+            .map_err(|_| format!("invalid count: {}", count))?
+    } else {
+        1
+    };
+
+    Ok((
+        select_columns(&fields, &columns.relation)?,
+        select_columns(&fields, &columns.head)?,
+        select_columns(&fields, &columns.dependent)?,
+        count,
+    ))
+}
+
+fn select_columns(fields: &[&str], columns: &[usize]) -> Result<String, String> {
+    let selected = columns
+        .iter()
+        .map(|&column| {
+            fields
+                .get(column - 1)
+                .cloned()
+                .ok_or_else(|| format!("line has no column {}", column))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(selected.join(" "))
+}
+
+/// Order associations by decreasing score, ties are broken by decreasing
+/// frequency and then by relation, head, and dependent.
+fn rank_order(a: &Association, b: &Association, measure: AssociationMeasure) -> Ordering {
+    let score_a = a.counts.score(measure);
+    let score_b = b.counts.score(measure);
+
+    score_b
+        .partial_cmp(&score_a)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| b.counts.joint.cmp(&a.counts.joint))
+        .then_with(|| a.relation.cmp(&b.relation))
+        .then_with(|| a.head.cmp(&b.head))
+        .then_with(|| a.dependent.cmp(&b.dependent))
+}
@@ -0,0 +1,267 @@
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::collections::BTreeMap;
+use std::env::args;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process;
+
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] [INSTANCE_FILE...]\n\n\
+         Evaluate PP attachment on extract-ambiguous-pps instances. Prints\n\
+         tab-separated accuracy@k and MRR of the baselines (and of the\n\
+         candidate scores with -s), overall and by field, preposition,\n\
+         and number of candidates. The rank baseline is only evaluated\n\
+         when training instances are given with -T.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+/// Attachment systems that are evaluated.
+#[derive(Clone, Copy)]
+enum System {
+    Scores,
+    Closest,
+    RightAssociation,
+    AlwaysVerb,
+    Rank,
+}
+
+impl System {
+    fn name(&self) -> &'static str {
+        match *self {
+            System::Scores => "scores",
+            System::Closest => "closest",
+            System::RightAssociation => "right-association",
+            System::AlwaysVerb => "always-verb",
+            System::Rank => "rank",
+        }
+    }
+}
+
+/// Metrics of a system, overall and broken down.
+struct SystemMetrics {
+    system: System,
+    all: RankingMetrics,
+    fields: BTreeMap<String, RankingMetrics>,
+    prepositions: BTreeMap<String, RankingMetrics>,
+    candidates: BTreeMap<usize, RankingMetrics>,
+}
+
+impl SystemMetrics {
+    fn new(system: System, max_k: usize) -> Self {
+        SystemMetrics {
+            system,
+            all: RankingMetrics::new(max_k),
+            fields: BTreeMap::new(),
+            prepositions: BTreeMap::new(),
+            candidates: BTreeMap::new(),
+        }
+    }
+
+    fn add(&mut self, instance: &PpInstance, ranking: &[usize]) {
+        let max_k = self.all.max_k();
+
+        self.all.add(instance, ranking);
+        self.fields
+            .entry(instance.preposition.field.clone())
+            .or_insert_with(|| RankingMetrics::new(max_k))
+            .add(instance, ranking);
+        self.prepositions
+            .entry(instance.preposition.form.to_lowercase())
+            .or_insert_with(|| RankingMetrics::new(max_k))
+            .add(instance, ranking);
+        self.candidates
+            .entry(instance.candidates.len())
+            .or_insert_with(|| RankingMetrics::new(max_k))
+            .add(instance, ranking);
+    }
+
+    fn write(&self, writer: &mut dyn Write) {
+        let name = self.system.name();
+
+        write_metrics(writer, name, "all", "-", &self.all);
+
+        for (field, metrics) in &self.fields {
+            write_metrics(writer, name, "field", field, metrics);
+        }
+
+        for (preposition, metrics) in &self.prepositions {
+            write_metrics(writer, name, "preposition", preposition, metrics);
+        }
+
+        for (n_candidates, metrics) in &self.candidates {
+            write_metrics(
+                writer,
+                name,
+                "candidates",
+                &n_candidates.to_string(),
+                metrics,
+            );
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag("F", "filename", "instances start with the input file name");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag(
+        "p",
+        "position",
+        "instances have the position of the preposition after the sentence identifier",
+    );
+    opts.optopt("k", "max-k", "report accuracy@1 up to accuracy@K (default: 3)", "K");
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "s",
+        "scores",
+        "candidates have a score column (predict-pp-backoff output)",
+    );
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme for verb tags, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optmulti(
+        "T",
+        "train",
+        "estimate the rank baseline from the instances in FILE",
+        "FILE",
+    );
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
+    let max_k = or_exit(matches.opt_get_default("k", 3));
+    if max_k == 0 {
+        stderr!("K should be at least 1");
+        process::exit(1);
+    }
+
+    let format = InstanceFormat {
+        source: matches.opt_present("F"),
+        position: matches.opt_present("p"),
+        scores: matches.opt_present("s"),
+    };
+
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+    let mut instances = Vec::new();
+    for input in &inputs {
+        read_instances(input, format, &mut instances);
+    }
+
+    // The rank prior is not estimated on the evaluation data, since that
+    // would leak the gold attachments into the baseline.
+    let mut rank_prior = RankPrior::new();
+    let train_files = matches.opt_strs("T");
+    let train_format = InstanceFormat {
+        scores: false,
+        ..format
+    };
+    for train_file in &train_files {
+        let mut train_instances = Vec::new();
+        read_instances(
+            &Input::File(PathBuf::from(train_file)),
+            train_format,
+            &mut train_instances,
+        );
+        train_instances
+            .iter()
+            .for_each(|instance| rank_prior.add(instance));
+    }
+
+    let mut systems = Vec::new();
+    if format.scores {
+        systems.push(SystemMetrics::new(System::Scores, max_k));
+    }
+    for &system in &[System::Closest, System::RightAssociation, System::AlwaysVerb] {
+        systems.push(SystemMetrics::new(system, max_k));
+    }
+    if !train_files.is_empty() {
+        systems.push(SystemMetrics::new(System::Rank, max_k));
+    }
+
+    for instance in &instances {
+        for metrics in &mut systems {
+            let ranking = match metrics.system {
+                System::Scores => score_ranking(instance),
+                System::Closest => closest_ranking(instance),
+                System::RightAssociation => right_association_ranking(instance),
+                System::AlwaysVerb => verb_ranking(instance, &scheme),
+                System::Rank => rank_prior.ranking(instance),
+            };
+
+            metrics.add(instance, &ranking);
+        }
+    }
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    or_exit(write!(writer, "system\tbreakdown\tgroup\tn"));
+    for k in 1..=max_k {
+        or_exit(write!(writer, "\tacc@{}", k));
+    }
+    or_exit(writeln!(writer, "\tmrr"));
+
+    for metrics in &systems {
+        metrics.write(&mut writer);
+    }
+
+    or_exit(writer.finish());
+}
+
+fn read_instances(input: &Input, format: InstanceFormat, instances: &mut Vec<PpInstance>) {
+    let reader = or_exit(input.buf_read());
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = or_exit(line);
+        instances.push(ok_or_continue!(or_warn(
+            PpInstance::parse(&line, format)
+                .map_err(|e| format!("{}, line {}: {}", input.name(), line_no + 1, e))
+        )));
+    }
+}
+
+fn write_metrics(
+    writer: &mut dyn Write,
+    system: &str,
+    breakdown: &str,
+    group: &str,
+    metrics: &RankingMetrics,
+) {
+    or_exit(write!(
+        writer,
+        "{}\t{}\t{}\t{}",
+        system,
+        breakdown,
+        group,
+        metrics.instances()
+    ));
+
+    for k in 1..=metrics.max_k() {
+        or_exit(write!(writer, "\t{:.4}", metrics.accuracy_at(k)));
+    }
+
+    or_exit(writeln!(writer, "\t{:.4}", metrics.mean_reciprocal_rank()));
+}
@@ -0,0 +1,151 @@
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::env::args;
+use std::io::Write;
+use std::process;
+
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] -r RULES [INPUT_FILE...]\n\n\
+         Extract dependents with ambiguous attachments, such as relative\n\
+         clauses, genitive attributes, adverbials, and conjuncts, with their\n\
+         candidate heads. Instances have the format of extract-ambiguous-pps,\n\
+         where the preposition columns hold the dependent and the object\n\
+         columns its object (or _). The built-in rules use TüBa-D/Z\n\
+         relations and STTS tags, use a rules file for other schemes. A\n\
+         rules file takes precedence over built-in rules with its name.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag(
+        "a",
+        "all",
+        "extract all dependents, including dependents with no head competition",
+    );
+    opts.optflag("F", "filename", "print the input file name of each dependent");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag("l", "lemma", "use lemmas instead of forms");
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "p",
+        "position",
+        "print the position of the dependent after the sentence identifier",
+    );
+    opts.optopt(
+        "r",
+        "rules",
+        &format!(
+            "candidate rules, a TüBa-D/Z preset ({}) or a TOML file",
+            ATTACHMENT_PRESETS.join(", ")
+        ),
+        "RULES",
+    );
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optopt("t", "threads", "number of worker threads (default: 1)", "N");
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    let rules_name = match matches.opt_str("r") {
+        Some(rules_name) => rules_name,
+        None => {
+            print_usage(&program, opts);
+            process::exit(1);
+        }
+    };
+    let rules = or_exit(AttachmentRules::preset_or_file(&rules_name));
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
+
+    // The built-in rules would silently find nothing in other schemes.
+    if AttachmentRules::preset(&rules_name).as_ref() == Some(&rules) &&
+        (scheme.relations != Relations::default() || scheme.tags != Tags::default())
+    {
+        stderr!(
+            "Warning: the {} rules use TüBa-D/Z relations and STTS tags, \
+             which differ from the annotation scheme",
+            rules_name
+        );
+    }
+
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    let pipeline = Pipeline::new(or_exit(matches.opt_get_default("t", 1)));
+
+    let all = matches.opt_present("a");
+    let filename = matches.opt_present("F");
+    let lemma = matches.opt_present("l");
+    let position = matches.opt_present("p");
+
+    or_exit(pipeline.run(
+        reader,
+        |sentence, graph| {
+            let mut output = Vec::new();
+
+            for ambiguity in attachment_ambiguities(graph, &scheme, &rules) {
+                if !all && ambiguity.candidates.len() == 1 {
+                    continue;
+                }
+
+                let mut instance = ambiguity.to_instance(graph, &scheme, &sentence.sent_id(), lemma);
+                if filename {
+                    instance.source = Some(sentence.source.clone());
+                }
+                if !position {
+                    instance.position = None;
+                }
+
+                instance.write(&mut output)?;
+            }
+
+            Ok(output)
+        },
+        |output| {
+            if let Some(output) = or_warn(output) {
+                writer.write_all(&output)?;
+            }
+
+            Ok(())
+        },
+    ));
+
+    or_exit(writer.finish());
+}
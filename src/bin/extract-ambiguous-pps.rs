@@ -1,6 +1,5 @@
 extern crate conllx;
 
-#[macro_use]
 extern crate syncooc;
 
 extern crate getopts;
@@ -10,86 +9,107 @@ extern crate maplit;
 
 extern crate petgraph;
 
-#[macro_use]
-extern crate lazy_static;
-
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashSet;
 use std::collections::HashMap;
-use std::io::{BufRead, Write};
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 use std::process;
 use std::env::args;
 
-use conllx::{Features, ReadSentence, Token};
+use conllx::{Token, WriteSentence};
 use syncooc::*;
 use getopts::Options;
-use petgraph::EdgeDirection;
 use petgraph::graph::NodeIndex;
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
-enum Field {
-    VF,
-    MF,
-    NF,
+/// Options for printing ambiguous PPs.
+struct PrintOptions<'a> {
+    scheme: &'a AnnotationScheme,
+    fields: &'a HashSet<TopologicalField>,
+    filename: bool,
+    lemma: bool,
+    all: bool,
+    position: bool,
+    quadruples: bool,
 }
 
-impl Field {
-    fn string_value(&self) -> &'static str {
+/// Reasons for dropping a PP.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum DropReason {
+    HeadWithoutTag,
+    IrrelevantHead,
+    NoField,
+    UnknownField,
+    ExcludedField,
+    NoComplement,
+    NoLeftBracket,
+    NoVerbalBracket,
+    NoClauseBracket,
+    CFieldWithoutHead,
+    UnexpectedField,
+    NoFiniteVerb,
+    HeadNotCandidate,
+    NoCompetition,
+    TokenWithoutForm,
+    TokenWithoutTag,
+    TokenWithoutField,
+}
+
+impl DropReason {
+    fn name(&self) -> &'static str {
         match *self {
-            Field::VF => "VF",
-            Field::MF => "MF",
-            Field::NF => "NF",
+            DropReason::HeadWithoutTag => "head-without-tag",
+            DropReason::IrrelevantHead => "irrelevant-head-tag",
+            DropReason::NoField => "no-field",
+            DropReason::UnknownField => "unknown-field",
+            DropReason::ExcludedField => "excluded-field",
+            DropReason::NoComplement => "no-complement",
+            DropReason::NoLeftBracket => "no-left-bracket",
+            DropReason::NoVerbalBracket => "no-verbal-bracket",
+            DropReason::NoClauseBracket => "no-clause-bracket",
+            DropReason::CFieldWithoutHead => "c-field-without-head",
+            DropReason::UnexpectedField => "unexpected-field",
+            DropReason::NoFiniteVerb => "no-finite-verb",
+            DropReason::HeadNotCandidate => "head-not-candidate",
+            DropReason::NoCompetition => "no-competition",
+            DropReason::TokenWithoutForm => "token-without-form",
+            DropReason::TokenWithoutTag => "token-without-tag",
+            DropReason::TokenWithoutField => "token-without-field",
         }
     }
 }
 
+/// An extracted value or the reason why a PP was dropped.
+type Extraction<T> = ::std::result::Result<T, DropReason>;
+
+/// Return the reason for dropping a PP when a value is missing.
+macro_rules! ok_or_drop {
+    ($expr:expr, $reason:expr) => (match $expr {
+        Some(val) => val,
+        None => return Ok(Err($reason)),
+    })
+}
+
 struct TrainingInstance<'a> {
     pub prep: NodeIndex,
     pub prep_obj: NodeIndex,
     pub candidates: Vec<CompetingHead<'a>>,
 }
 
-static PREP_COMPL_RELATION: &'static str = "PN";
-
-static PP_RELATION: &'static str = "PP";
-
-static AUXILIARY_RELATION: &'static str = "AUX";
-
-static TOPO_FIELD_FEATURE: &'static str = "tf";
-
-static TOPO_LK_BRACKET: &'static str = "LK";
-
-static TOPO_RK_FIELD: &'static str = "VC";
-
-static TOPO_C_FIELD: &'static str = "C";
-
-static TOPO_UNKNOWN_FIELD: &'static str = "UK";
-
-static FINITE_VERB_TAG: &'static str = "VVFIN";
-
-static FINITE_AUXILIARY_TAG: &'static str = "VAFIN";
-
-static FINITE_MODAL_TAG: &'static str = "VMFIN";
-
-lazy_static! {
-    static ref FINITE_VERB_TAGS: HashSet<&'static str> = hashset!{
-        FINITE_VERB_TAG,
-        FINITE_AUXILIARY_TAG,
-        FINITE_MODAL_TAG
-    };
-
-    static ref STRING_FIELD: HashMap<&'static str, Field> = hashmap!{
-        "VF" => Field::VF,
-        "MF" => Field::MF,
-        "NF" => Field::NF
-    };
-}
-
-fn relevant_head_tag(tag: &str) -> bool {
-    tag.starts_with("N") || tag.starts_with("V")
+fn relevant_head_tag(scheme: &AnnotationScheme, tag: &str) -> bool {
+    scheme.is_noun_tag(tag) || scheme.is_verb_tag(tag)
 }
 
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [options] [INPUT_FILE] [OUTPUT_FILE]", program);
+    let brief = format!(
+        "Usage: {} [options] [INPUT_FILE...]\n\n{}",
+        program, INPUTS_USAGE
+    );
     stderr!("{}", opts.usage(&brief));
 }
 
@@ -107,10 +127,58 @@ fn main() {
         "all",
         "extract all PPs, including PPs with no head competition",
     );
+    opts.optflag(
+        "d",
+        "diagnostics",
+        "print how many PPs were dropped for each reason",
+    );
+    opts.optopt(
+        "D",
+        "dump",
+        "with --diagnostics, write the sentences with dropped PPs to DIR/REASON.conll",
+        "DIR",
+    );
+    opts.optflag(
+        "e",
+        "errors",
+        "print the attachments of the parser tree in the PHEAD/PDEPREL columns (CoNLL-X only)",
+    );
+    opts.optflag(
+        "E",
+        "error-summary",
+        "print a summary of parser attachment errors by candidate rank and field",
+    );
+    opts.optflag("F", "filename", "print the input file name of each PP");
     opts.optopt("f", "field", "field to extract from", "FIELD");
     opts.optflag("h", "help", "print this help menu");
     opts.optflag("l", "lemma", "use lemmas instead of forms");
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "p",
+        "position",
+        "print the position of the preposition after the sentence identifier",
+    );
+    opts.optflag(
+        "q",
+        "quadruples",
+        "print V N1 P N2 LABEL quadruples of the closest verb and noun candidates",
+    );
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
     opts.optflag("s", "stats", "print dataset statistics");
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optopt("t", "threads", "number of worker threads (default: 1)", "N");
     let matches = or_exit(opts.parse(&args[1..]));
 
     if matches.opt_present("h") {
@@ -118,121 +186,476 @@ fn main() {
         return;
     }
 
-    if matches.free.len() > 2 {
-        print_usage(&program, opts);
+    let fields = field_to_set(matches.opt_str("f"));
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
+
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+
+    if matches.opt_present("D") && !matches.opt_present("d") {
+        stderr!("-D can only be used with -d");
         process::exit(1);
     }
 
-    let fields = field_to_set(matches.opt_str("f"));
+    let parser_errors = matches.opt_present("e") || matches.opt_present("E");
+    if parser_errors {
+        for &opt in &["p", "q"] {
+            if matches.opt_present(opt) {
+                stderr!("-{} cannot be used with -e or -E", opt);
+                process::exit(1);
+            }
+        }
 
-    // Read CoNNL-X from stdin or file.
-    let input = or_stdin(matches.free.get(0));
-    let reader = conllx::Reader::new(or_exit(input.buf_read()));
+        let conllu = matches.opt_present("u")
+            || inputs
+                .iter()
+                .any(|input| InputFormat::detect(input) == InputFormat::Conllu);
+        if conllu {
+            stderr!(
+                "-e and -E read the parser tree from the PHEAD column, \
+                 which CoNLL-U does not have"
+            );
+            process::exit(1);
+        }
+    }
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
 
-    let output = or_stdout(matches.free.get(1));
+    let output = or_stdout(matches.opt_str("o").as_ref());
     let mut writer = or_exit(output.buf_write());
 
+    let pipeline = Pipeline::new(or_exit(matches.opt_get_default("t", 1)));
+
+    let options = PrintOptions {
+        scheme: &scheme,
+        fields: &fields,
+        filename: matches.opt_present("F"),
+        lemma: matches.opt_present("l"),
+        all: matches.opt_present("a"),
+        position: matches.opt_present("p"),
+        quadruples: matches.opt_present("q"),
+    };
+
     if matches.opt_present("s") {
-        print_statistics(reader, &fields);
-    } else {
-        print_ambiguous_pps(
+        print_statistics(pipeline, reader, &mut writer, &scheme, &fields);
+    } else if matches.opt_present("d") {
+        print_diagnostics(pipeline, reader, &mut writer, &options, matches.opt_str("D"));
+    } else if parser_errors {
+        print_parser_errors(
+            pipeline,
             reader,
             &mut writer,
-            matches.opt_present("l"),
-            matches.opt_present("a"),
-            &fields,
+            &options,
+            matches.opt_present("E"),
         );
+    } else {
+        print_ambiguous_pps(pipeline, reader, &mut writer, &options);
     }
 
-
+    or_exit(writer.finish());
 }
 
-fn print_statistics<R>(reader: conllx::Reader<R>, fields: &HashSet<Field>)
-where
-    R: BufRead,
-{
+fn print_statistics(
+    pipeline: Pipeline,
+    reader: CorpusReader,
+    writer: &mut dyn Write,
+    scheme: &AnnotationScheme,
+    fields: &HashSet<TopologicalField>,
+) {
     let mut n_relevant_tags = 0;
     let mut n_instances = 0;
     let mut n_candidate_heads = 0;
 
-    for sentence in reader {
-        let sentence = or_exit(sentence);
-        let graph = sentence_to_graph(&sentence, false);
+    or_exit(pipeline.run(
+        reader,
+        |sentence, graph| {
+            let n_relevant_tags_sent = sentence
+                .sentence
+                .iter()
+                .filter(|t| scheme.tag(t).map(|pos| relevant_head_tag(scheme, pos)).unwrap_or(false))
+                .count();
 
-        let n_relevant_tags_sent = sentence
-            .iter()
-            .filter(|t| t.pos().map(relevant_head_tag).unwrap_or(false))
-            .count();
+            let instances = extract_ambiguous_pps(graph, scheme, false, fields)?;
+            let n_candidate_heads_sent: usize =
+                instances.iter().map(|i| i.candidates.len()).sum();
 
-        for instance in extract_ambiguous_pps(&graph, false, fields) {
-            n_candidate_heads += instance.candidates.len();
-            n_relevant_tags += n_relevant_tags_sent;
-            n_instances += 1;
-        }
-    }
+            Ok((n_relevant_tags_sent, instances.len(), n_candidate_heads_sent))
+        },
+        |counts| {
+            if let Some((n_relevant_tags_sent, n_instances_sent, n_candidate_heads_sent)) =
+                or_warn(counts)
+            {
+                n_relevant_tags += n_relevant_tags_sent * n_instances_sent;
+                n_instances += n_instances_sent;
+                n_candidate_heads += n_candidate_heads_sent;
+            }
+
+            Ok(())
+        },
+    ));
 
-    println!("Instances: {}", n_instances);
-    println!(
+    or_exit(writeln!(writer, "Instances: {}", n_instances));
+    or_exit(writeln!(
+        writer,
         "Average relevant tags: {:.2}",
         n_relevant_tags as f64 / n_instances as f64
-    );
-    println!(
+    ));
+    or_exit(writeln!(
+        writer,
         "Average candidate heads: {:.2}",
         n_candidate_heads as f64 / n_instances as f64
-    );
+    ));
 }
 
-fn print_ambiguous_pps<R>(
-    reader: conllx::Reader<R>,
-    writer: &mut Write,
-    lemma: bool,
-    all: bool,
-    fields: &HashSet<Field>,
-) where
-    R: BufRead,
-{
-    for (sent_id, sentence) in reader.sentences().enumerate() {
-        let sentence = or_exit(sentence);
-        let graph = sentence_to_graph(&sentence, false);
+/// Print how many PPs were dropped for each reason. PPs that are dropped
+/// because a printed column is missing are counted as well, so that the
+/// number of instances is the number of lines that are printed without
+/// `--diagnostics`.
+fn print_diagnostics(
+    pipeline: Pipeline,
+    reader: CorpusReader,
+    writer: &mut dyn Write,
+    options: &PrintOptions,
+    dump_dir: Option<String>,
+) {
+    let scheme = options.scheme;
+
+    if let Some(ref dump_dir) = dump_dir {
+        or_exit(fs::create_dir_all(dump_dir));
+    }
+
+    let mut n_pps = 0;
+    let mut n_instances = 0;
+    let mut drops = BTreeMap::new();
+    let mut dumps = HashMap::new();
+
+    or_exit(pipeline.run(
+        reader,
+        |sentence, graph| {
+            let pps = classify_pps(graph, scheme, options.all, options.fields)?;
+            let n_pps = pps.len();
+            let reasons: Vec<_> = pps
+                .into_iter()
+                .filter_map(|(_, pp)| match pp {
+                    Ok(instance) => instance_columns(graph, &instance, scheme, options.lemma).err(),
+                    Err(reason) => Some(reason),
+                })
+                .collect();
+
+            let conllx = if dump_dir.is_some() && !reasons.is_empty() {
+                let mut conllx = Vec::new();
+                ConllxWriter::new(&mut conllx).write_sentence(&sentence.sentence)?;
+                Some(conllx)
+            } else {
+                None
+            };
+
+            Ok((n_pps, reasons, conllx))
+        },
+        |result| {
+            let (n_pps_sent, reasons, conllx) = match or_warn(result) {
+                Some(result) => result,
+                None => return Ok(()),
+            };
+
+            n_pps += n_pps_sent;
+            n_instances += n_pps_sent - reasons.len();
+            for &reason in &reasons {
+                *drops.entry(reason).or_insert(0) += 1;
+            }
+
+            if let (Some(dump_dir), Some(conllx)) = (dump_dir.as_ref(), conllx) {
+                let unique_reasons: BTreeSet<_> = reasons.into_iter().collect();
+                for reason in unique_reasons {
+                    let dump = match dumps.entry(reason) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let path =
+                                Path::new(dump_dir).join(format!("{}.conll", reason.name()));
+                            entry.insert(BufWriter::new(File::create(path)?))
+                        }
+                    };
+
+                    dump.write_all(&conllx)?;
+                }
+            }
+
+            Ok(())
+        },
+    ));
+
+    for dump in dumps.values_mut() {
+        or_exit(dump.flush());
+    }
 
-        print_graph_ambiguous_pps(writer, sent_id + 1, &graph, lemma, all, fields)
+    or_exit(writeln!(writer, "PPs\t{}", n_pps));
+    or_exit(writeln!(writer, "instances\t{}", n_instances));
+
+    let mut drops: Vec<_> = drops.into_iter().collect();
+    drops.sort_by_key(|&(_, count)| Reverse(count));
+    for (reason, count) in drops {
+        or_exit(writeln!(writer, "{}\t{}", reason.name(), count));
     }
 }
 
-fn print_graph_ambiguous_pps(
-    writer: &mut Write,
-    sent_id: usize,
-    graph: &DependencyGraph,
-    lemma: bool,
-    all: bool,
-    fields: &HashSet<Field>,
+fn print_ambiguous_pps(
+    pipeline: Pipeline,
+    reader: CorpusReader,
+    writer: &mut dyn Write,
+    options: &PrintOptions,
 ) {
-    for instance in extract_ambiguous_pps(graph, all, fields) {
-        let prep = graph[instance.prep].token;
-        let prep_obj = graph[instance.prep_obj].token;
+    or_exit(pipeline.run(
+        reader,
+        |sentence, graph| {
+            let source = if options.filename {
+                Some(sentence.source.as_str())
+            } else {
+                None
+            };
 
-        or_exit(write!(
+            let mut output = Vec::new();
+            if options.quadruples {
+                print_graph_quadruples(&mut output, source, &sentence.sent_id(), graph, options)?;
+            } else {
+                print_graph_ambiguous_pps(
+                    &mut output,
+                    source,
+                    &sentence.sent_id(),
+                    graph,
+                    options,
+                )?;
+            }
+            Ok(output)
+        },
+        |output| {
+            if let Some(output) = or_warn(output) {
+                writer.write_all(&output)?;
+            }
+
+            Ok(())
+        },
+    ));
+}
+
+/// The attachment of a PP instance in a parser tree.
+struct ParserAttachment {
+    source: Option<String>,
+    sent_id: String,
+    prep_offset: usize,
+    preposition: String,
+    field: String,
+    gold_rank: isize,
+    predicted_rank: Option<isize>,
+    correct: bool,
+}
+
+fn print_parser_errors(
+    pipeline: Pipeline,
+    reader: CorpusReader,
+    writer: &mut dyn Write,
+    options: &PrintOptions,
+    summary: bool,
+) {
+    let mut attachments = Vec::new();
+
+    or_exit(pipeline.run(
+        reader,
+        |sentence, graph| parser_attachments(sentence, graph, options),
+        |sent_attachments| {
+            let sent_attachments = match or_warn(sent_attachments) {
+                Some(sent_attachments) => sent_attachments,
+                None => return Ok(()),
+            };
+
+            if summary {
+                attachments.extend(sent_attachments);
+                return Ok(());
+            }
+
+            for attachment in sent_attachments {
+                if let Some(ref source) = attachment.source {
+                    write!(writer, "{} ", source)?;
+                }
+
+                writeln!(
+                    writer,
+                    "{} {} {} {} {} {} {}",
+                    attachment.sent_id,
+                    attachment.prep_offset + 1,
+                    attachment.preposition,
+                    attachment.field,
+                    attachment.gold_rank,
+                    attachment
+                        .predicted_rank
+                        .map(|rank| rank.to_string())
+                        .unwrap_or_else(|| "_".to_owned()),
+                    if attachment.correct { 1 } else { 0 }
+                )?;
+            }
+
+            Ok(())
+        },
+    ));
+
+    if summary {
+        print_error_summary(writer, &attachments);
+    }
+}
+
+/// Compare the gold attachments of PP instances to the attachments in
+/// the parser tree of the projective head columns.
+fn parser_attachments(
+    sentence: &CorpusSentence,
+    graph: &DependencyGraph,
+    options: &PrintOptions,
+) -> Result<Vec<ParserAttachment>> {
+    let scheme = options.scheme;
+    let predicted_graph = sentence_to_graph(&sentence.sentence, true, false)?;
+
+    let mut attachments = Vec::new();
+
+    for instance in extract_ambiguous_pps(graph, scheme, options.all, options.fields)? {
+        let prep_node = &graph[instance.prep];
+        let ranks = compute_ranks(prep_node.offset, &instance.candidates);
+
+        let gold_rank = ok_or_continue!(
+            ranks
+                .iter()
+                .zip(&instance.candidates)
+                .find(|&(_, candidate)| candidate.head)
+                .map(|(&rank, _)| rank)
+        );
+
+        // Node indices are token offsets in both graphs. Verb candidates
+        // are full verbs, so the predicted head is also resolved through
+        // auxiliaries.
+        let predicted_rank = ancestor_tokens(&predicted_graph, instance.prep)
+            .next()
+            .and_then(|head| {
+                let verb = resolve_verb(&predicted_graph, scheme, head);
+                ranks
+                    .iter()
+                    .zip(&instance.candidates)
+                    .find(|&(_, candidate)| {
+                        candidate.node.offset == head.index()
+                            || candidate.node.offset == verb.index()
+                    })
+                    .map(|(&rank, _)| rank)
+            });
+
+        attachments.push(ParserAttachment {
+            source: if options.filename {
+                Some(sentence.source.clone())
+            } else {
+                None
+            },
+            sent_id: sentence.sent_id(),
+            prep_offset: prep_node.offset,
+            preposition: ok_or_continue!(extract_form(prep_node.token, options.lemma)).to_owned(),
+            field: ok_or_continue!(feature_value(
+                prep_node.token,
+                &scheme.topological_fields.feature
+            )),
+            gold_rank,
+            predicted_rank,
+            correct: predicted_rank == Some(gold_rank),
+        });
+    }
+
+    Ok(attachments)
+}
+
+fn print_error_summary(writer: &mut dyn Write, attachments: &[ParserAttachment]) {
+    let mut accuracy = BTreeMap::new();
+    let mut confusion = BTreeMap::new();
+
+    for attachment in attachments {
+        for field in &[attachment.field.as_str(), "all"] {
+            let counts = accuracy.entry(field.to_string()).or_insert((0, 0));
+            counts.0 += 1;
+            if attachment.correct {
+                counts.1 += 1;
+            }
+        }
+
+        let predicted_rank = attachment
+            .predicted_rank
+            .map(|rank| rank.to_string())
+            .unwrap_or_else(|| "_".to_owned());
+        *confusion
+            .entry((
+                attachment.field.clone(),
+                attachment.gold_rank,
+                predicted_rank,
+            ))
+            .or_insert(0) += 1;
+    }
+
+    or_exit(writeln!(writer, "field\tinstances\tcorrect\taccuracy"));
+    for (field, (instances, correct)) in accuracy {
+        or_exit(writeln!(
             writer,
-            "{} {} {} {} {} {} {}",
-            sent_id,
-            ok_or_continue!(extract_form(&prep, lemma)),
-            ok_or_continue!(prep.pos()),
-            ok_or_continue!(feature_value(&prep, "tf")),
-            ok_or_continue!(extract_form(&prep_obj, lemma)),
-            ok_or_continue!(prep_obj.pos()),
-            ok_or_continue!(feature_value(&prep_obj, "tf"))
+            "{}\t{}\t{}\t{:.4}",
+            field,
+            instances,
+            correct,
+            correct as f64 / instances as f64
+        ));
+    }
+
+    or_exit(writeln!(writer));
+
+    or_exit(writeln!(writer, "field\tgold_rank\tpredicted_rank\tcount"));
+    for ((field, gold_rank, predicted_rank), count) in confusion {
+        or_exit(writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            field, gold_rank, predicted_rank, count
         ));
+    }
+}
+
+fn print_graph_ambiguous_pps(
+    writer: &mut dyn Write,
+    source: Option<&str>,
+    sent_id: &str,
+    graph: &DependencyGraph,
+    options: &PrintOptions,
+) -> Result<()> {
+    let scheme = options.scheme;
+    let lemma = options.lemma;
+
+    for instance in extract_ambiguous_pps(graph, scheme, options.all, options.fields)? {
+        let columns = ok_or_continue!(instance_columns(graph, &instance, scheme, lemma).ok());
+
+        if let Some(source) = source {
+            or_exit(write!(writer, "{} ", source));
+        }
+
+        or_exit(write!(writer, "{}", sent_id));
+
+        if options.position {
+            or_exit(write!(writer, " {}", graph[instance.prep].offset + 1));
+        }
+
+        or_exit(write!(writer, " {} {}", columns[0], columns[1]));
 
         let ranks = compute_ranks(graph[instance.prep].offset, &instance.candidates);
 
-        for (rank, candidate) in ranks.iter().zip(instance.candidates) {
-            let token = candidate.node.token;
+        for ((rank, candidate), columns) in ranks
+            .iter()
+            .zip(&instance.candidates)
+            .zip(&columns[2..])
+        {
             or_exit(write!(
                 writer,
-                " {} {} {} {} {} {}",
-                ok_or_continue!(extract_form(&token, lemma)),
-                ok_or_continue!(token.pos()),
-                ok_or_continue!(feature_value(&token, "tf")),
+                " {} {} {} {}",
+                columns,
                 candidate.node.offset as isize -
                     graph[instance.prep].offset as isize,
                 rank,
@@ -240,81 +663,221 @@ fn print_graph_ambiguous_pps(
             ));
         }
 
-        or_exit(writeln!(writer, ""));
+        or_exit(writeln!(writer));
     }
 
+    Ok(())
+}
+
+/// The form, tag, and topological field columns of the preposition, its
+/// complement, and the candidates of an instance, in that order.
+fn instance_columns(
+    graph: &DependencyGraph,
+    instance: &TrainingInstance,
+    scheme: &AnnotationScheme,
+    lemma: bool,
+) -> Extraction<Vec<String>> {
+    let pp_tokens = [graph[instance.prep].token, graph[instance.prep_obj].token];
+
+    pp_tokens
+        .iter()
+        .cloned()
+        .chain(instance.candidates.iter().map(|candidate| candidate.node.token))
+        .map(|token| {
+            let form = extract_form(token, lemma).ok_or(DropReason::TokenWithoutForm)?;
+            let tag = scheme.tag(token).ok_or(DropReason::TokenWithoutTag)?;
+            let field = feature_value(token, &scheme.topological_fields.feature)
+                .ok_or(DropReason::TokenWithoutField)?;
+            Ok(format!("{} {} {}", form, tag, field))
+        })
+        .collect()
+}
+
+/// Print instances as V N1 P N2 quadruples (Ratnaparkhi et al., 1994).
+///
+/// The quadruple consists of the verb and noun candidates that are
+/// closest to the preposition, the preposition, and its complement. The
+/// label is `V` when the PP is attached to the verb and `N` when it is
+/// attached to the noun. Instances that are attached to another
+/// candidate are skipped.
+fn print_graph_quadruples(
+    writer: &mut dyn Write,
+    source: Option<&str>,
+    sent_id: &str,
+    graph: &DependencyGraph,
+    options: &PrintOptions,
+) -> Result<()> {
+    let scheme = options.scheme;
+    let lemma = options.lemma;
+
+    for instance in extract_ambiguous_pps(graph, scheme, options.all, options.fields)? {
+        let p_offset = graph[instance.prep].offset;
+
+        let verb = ok_or_continue!(closest_candidate(scheme, &instance.candidates, p_offset, |pos| {
+            scheme.is_verb_tag(pos)
+        }));
+        let noun = ok_or_continue!(closest_candidate(scheme, &instance.candidates, p_offset, |pos| {
+            scheme.is_noun_tag(pos)
+        }));
+
+        let label = if verb.head {
+            "V"
+        } else if noun.head {
+            "N"
+        } else {
+            continue;
+        };
+
+        let prep = graph[instance.prep].token;
+        let prep_obj = graph[instance.prep_obj].token;
+
+        let quadruple = [
+            ok_or_continue!(extract_form(verb.node.token, lemma)),
+            ok_or_continue!(extract_form(noun.node.token, lemma)),
+            ok_or_continue!(extract_form(prep, lemma)),
+            ok_or_continue!(extract_form(prep_obj, lemma)),
+        ];
+
+        if let Some(source) = source {
+            or_exit(write!(writer, "{} ", source));
+        }
+
+        or_exit(writeln!(
+            writer,
+            "{} {} {}",
+            sent_id,
+            quadruple.join(" "),
+            label
+        ));
+    }
+
+    Ok(())
+}
+
+/// Find the candidate with a matching tag that is closest to the
+/// preposition.
+fn closest_candidate<'a, 'b, F>(
+    scheme: &AnnotationScheme,
+    candidates: &'b [CompetingHead<'a>],
+    p_offset: usize,
+    tag_predicate: F,
+) -> Option<&'b CompetingHead<'a>>
+where
+    F: Fn(&str) -> bool,
+{
+    candidates
+        .iter()
+        .filter(|candidate| {
+            scheme.tag(candidate.node.token).map(&tag_predicate).unwrap_or(false)
+        })
+        .min_by_key(|candidate| (candidate.node.offset as isize - p_offset as isize).abs())
 }
 
 fn extract_ambiguous_pps<'a>(
     graph: &'a DependencyGraph<'a>,
+    scheme: &AnnotationScheme,
+    all: bool,
+    fields: &HashSet<TopologicalField>,
+) -> Result<Vec<TrainingInstance<'a>>> {
+    Ok(classify_pps(graph, scheme, all, fields)?
+        .into_iter()
+        .filter_map(|(_, pp)| pp.ok())
+        .collect())
+}
+
+/// Extract the PPs of a graph. Returns the preposition of every PP,
+/// together with its instance or the reason why it was dropped.
+fn classify_pps<'a>(
+    graph: &'a DependencyGraph<'a>,
+    scheme: &AnnotationScheme,
     all: bool,
-    fields: &HashSet<Field>,
-) -> Vec<TrainingInstance<'a>> {
-    let mut instances = Vec::new();
+    fields: &HashSet<TopologicalField>,
+) -> Result<Vec<(NodeIndex, Extraction<TrainingInstance<'a>>)>> {
+    let mut pps = Vec::new();
 
     for edge in graph.raw_edges() {
         // Find PPs in the graph
-        if edge.weight != DependencyEdge::Relation(Some(PP_RELATION)) {
-            continue;
+        match edge.weight {
+            DependencyEdge::Relation(Some(relation)) if scheme.is_pp_relation(relation) => (),
+            _ => continue,
         }
 
-        let head = graph[edge.source()].token;
-        let head_pos = ok_or_continue!(head.pos());
+        let pp = classify_pp(graph, scheme, all, fields, edge.source(), edge.target())?;
+        pps.push((edge.target(), pp));
+    }
 
-        // Skip PPs with heads that we are not interested in
-        if !relevant_head_tag(head_pos) {
-            continue;
-        }
+    Ok(pps)
+}
 
-        let pp_node = &graph[edge.target()];
+fn classify_pp<'a>(
+    graph: &'a DependencyGraph<'a>,
+    scheme: &AnnotationScheme,
+    all: bool,
+    fields: &HashSet<TopologicalField>,
+    head_idx: NodeIndex,
+    p_idx: NodeIndex,
+) -> Result<Extraction<TrainingInstance<'a>>> {
+    let head = graph[head_idx].token;
+    let head_pos = ok_or_drop!(scheme.tag(head), DropReason::HeadWithoutTag);
 
-        let pp_field = ok_or_continue!(feature_value(pp_node.token, TOPO_FIELD_FEATURE));
+    // Skip PPs with heads that we are not interested in
+    if !relevant_head_tag(scheme, head_pos) {
+        return Ok(Err(DropReason::IrrelevantHead));
+    }
 
-        let field = ok_or_continue!(STRING_FIELD.get(pp_field.as_str()));
+    let pp_node = &graph[p_idx];
 
-        if !fields.contains(field) {
-            // Skip PPs that are not in the fields that we are interested in.
-            continue;
-        }
+    let pp_field = ok_or_drop!(
+        feature_value(pp_node.token, &scheme.topological_fields.feature),
+        DropReason::NoField
+    );
 
-        let pn_rel = ok_or_continue!(first_matching_edge(
-            graph,
-            edge.target(),
-            EdgeDirection::Outgoing,
-            |e| {
-                *e == DependencyEdge::Relation(Some(PREP_COMPL_RELATION))
-            },
-        ));
+    let field = match TopologicalField::from_label(scheme, &pp_field) {
+        Some(field @ TopologicalField::VF) |
+        Some(field @ TopologicalField::MF) |
+        Some(field @ TopologicalField::NF) => field,
+        _ => return Ok(Err(DropReason::UnknownField)),
+    };
 
-        let competition =
-            match *field {
-                Field::VF => {
-                    ok_or_continue!(find_competition_vf(graph, edge.target(), edge.source()))
-                }                
-                Field::MF => {
-                    ok_or_continue!(find_competition_mf(graph, edge.target(), edge.source()))
-                }
-                Field::NF => {
-                    ok_or_continue!(find_competition_nf(graph, edge.target(), edge.source()))
-                }
-            };
+    if !fields.contains(&field) {
+        // Skip PPs that are not in the fields that we are interested in.
+        return Ok(Err(DropReason::ExcludedField));
+    }
 
-        // Don't print when there is no ambiguity.
-        if !competition.iter().any(|x| x.head) || (!all && competition.len() == 1) {
-            continue;
-        }
+    let pn_rel = ok_or_drop!(
+        preposition_complement(graph, scheme, p_idx),
+        DropReason::NoComplement
+    );
 
-        instances.push(TrainingInstance {
-            prep: edge.target(),
-            prep_obj: pn_rel,
-            candidates: competition,
-        });
+    let competition = match field {
+        TopologicalField::VF => find_competition_vf(graph, scheme, p_idx, head_idx)?,
+        TopologicalField::MF => find_competition_mf(graph, scheme, p_idx, head_idx)?,
+        TopologicalField::NF => find_competition_nf(graph, scheme, p_idx, head_idx)?,
+        _ => unreachable!(),
+    };
+
+    let competition = match competition {
+        Ok(competition) => competition,
+        Err(reason) => return Ok(Err(reason)),
+    };
+
+    // Don't print when there is no ambiguity.
+    if !competition.iter().any(|x| x.head) {
+        return Ok(Err(DropReason::HeadNotCandidate));
     }
 
-    instances
+    if !all && competition.len() == 1 {
+        return Ok(Err(DropReason::NoCompetition));
+    }
+
+    Ok(Ok(TrainingInstance {
+        prep: p_idx,
+        prep_obj: pn_rel,
+        candidates: competition,
+    }))
 }
 
-fn compute_ranks(p_offset: usize, competition: &Vec<CompetingHead>) -> Vec<isize> {
+fn compute_ranks(p_offset: usize, competition: &[CompetingHead]) -> Vec<isize> {
     let indices: Vec<_> = (0..competition.len()).collect();
 
     let mut before: Vec<_> = indices
@@ -354,9 +917,10 @@ struct CompetingHead<'a> {
 
 fn find_competition_vf<'a>(
     graph: &'a DependencyGraph<'a>,
+    scheme: &AnnotationScheme,
     p_idx: NodeIndex,
     head_idx: NodeIndex,
-) -> Option<Vec<CompetingHead>> {
+) -> Result<Extraction<Vec<CompetingHead<'a>>>> {
     let mut candidates = Vec::new();
 
     // Exclude cases where the head is left of the PP.
@@ -365,18 +929,14 @@ fn find_competition_vf<'a>(
     // }
 
     // Find left bracket
-    let lk_idx = try_ok!(adjacent_tokens(graph, p_idx, Direction::Succeeding).find(
-        |idx| {
-            let node = &graph[*idx];
-
-            match feature_value(node.token, TOPO_FIELD_FEATURE) {
-                Some(field) => field == TOPO_LK_BRACKET,
-                None => false,
-            }
-        },
-    ));
+    let lk_idx = ok_or_drop!(
+        adjacent_tokens(graph, p_idx, Direction::Succeeding).find(|idx| {
+            graph[*idx].topological_field(scheme) == Some(TopologicalField::LK)
+        }),
+        DropReason::NoLeftBracket
+    );
 
-    let verb_idx = resolve_verb(graph, lk_idx);
+    let verb_idx = resolve_verb(graph, scheme, lk_idx);
 
     candidates.push(CompetingHead {
         node: &graph[verb_idx],
@@ -387,41 +947,42 @@ fn find_competition_vf<'a>(
     });
 
     let preceding_is_noun = match adjacent_tokens(graph, p_idx, Direction::Preceeding).next() {
-        Some(prec_idx) => graph[prec_idx].token.pos().unwrap().starts_with("N"),
+        Some(prec_idx) => scheme.is_noun_tag(node_pos(scheme, &graph[prec_idx])?),
         None => false,
     };
 
     let vf_tokens = adjacent_tokens(graph, p_idx, Direction::Preceeding).take_while(|idx| {
-        match feature_value(&graph[*idx].token, TOPO_FIELD_FEATURE) {
-            Some(field) => field == Field::VF.string_value() || field == TOPO_UNKNOWN_FIELD,
-            None => false,
-        }
+        matches!(
+            graph[*idx].topological_field(scheme),
+            Some(TopologicalField::VF) | Some(TopologicalField::UK)
+        )
     });
 
-    add_tokens(graph, head_idx, vf_tokens, &mut candidates);
+    add_tokens(graph, scheme, head_idx, vf_tokens, &mut candidates);
 
 
     // Only add MF tokens when the preceding token is not a noun...
     if !preceding_is_noun {
         // Left bracket should not contain any other material...
         let mf_tokens = adjacent_tokens(graph, lk_idx, Direction::Succeeding).take_while(|idx| {
-            match feature_value(&graph[*idx].token, TOPO_FIELD_FEATURE) {
-                Some(field) => field == Field::MF.string_value() || field == TOPO_UNKNOWN_FIELD,
-                None => false,
-            }
+            matches!(
+                graph[*idx].topological_field(scheme),
+                Some(TopologicalField::MF) | Some(TopologicalField::UK)
+            )
         });
 
-        add_tokens(graph, head_idx, mf_tokens, &mut candidates);
+        add_tokens(graph, scheme, head_idx, mf_tokens, &mut candidates);
     }
 
-    Some(candidates)
+    Ok(Ok(candidates))
 }
 
 fn find_competition_nf<'a>(
     graph: &'a DependencyGraph<'a>,
+    scheme: &AnnotationScheme,
     p_idx: NodeIndex,
     head_idx: NodeIndex,
-) -> Option<Vec<CompetingHead>> {
+) -> Result<Extraction<Vec<CompetingHead<'a>>>> {
     let mut candidates = Vec::new();
 
     // Exclude cases where the head is left of the PP.
@@ -430,22 +991,25 @@ fn find_competition_nf<'a>(
     // }
 
     // Find a bracket
-    let bracket_idx = try_ok!(adjacent_tokens(graph, p_idx, Direction::Preceeding).find(
-        |idx| {
-            let node = &graph[*idx];
+    let mut bracket_idx = None;
+    for idx in adjacent_tokens(graph, p_idx, Direction::Preceeding) {
+        let node = &graph[idx];
 
-            let pos = node.token.pos().unwrap();
+        let pos = node_pos(scheme, node)?;
 
-            match feature_value(node.token, TOPO_FIELD_FEATURE) {
-                Some(field) => {
-                    (field == TOPO_RK_FIELD || field == TOPO_LK_BRACKET) && pos.starts_with("V")
-                }
-                None => false,
-            }
-        },
-    ));
+        let is_bracket = match node.topological_field(scheme) {
+            Some(TopologicalField::VC) | Some(TopologicalField::LK) => scheme.is_verb_tag(pos),
+            _ => false,
+        };
+
+        if is_bracket {
+            bracket_idx = Some(idx);
+            break;
+        }
+    }
+    let bracket_idx = ok_or_drop!(bracket_idx, DropReason::NoVerbalBracket);
 
-    let verb_idx = resolve_verb(graph, bracket_idx);
+    let verb_idx = resolve_verb(graph, scheme, bracket_idx);
 
     candidates.push(CompetingHead {
         node: &graph[verb_idx],
@@ -456,49 +1020,49 @@ fn find_competition_nf<'a>(
     });
 
     let preceding_is_noun = match adjacent_tokens(graph, p_idx, Direction::Preceeding).next() {
-        Some(prec_idx) => graph[prec_idx].token.pos().unwrap().starts_with("N"),
+        Some(prec_idx) => scheme.is_noun_tag(node_pos(scheme, &graph[prec_idx])?),
         None => false,
     };
 
     let nf_tokens = adjacent_tokens(graph, p_idx, Direction::Preceeding).take_while(|idx| {
-        match feature_value(&graph[*idx].token, TOPO_FIELD_FEATURE) {
-            Some(field) => field == Field::NF.string_value() || field == TOPO_UNKNOWN_FIELD,
-            None => false,
-        }
+        matches!(
+            graph[*idx].topological_field(scheme),
+            Some(TopologicalField::NF) | Some(TopologicalField::UK)
+        )
     });
 
-    add_tokens(graph, head_idx, nf_tokens, &mut candidates);
+    add_tokens(graph, scheme, head_idx, nf_tokens, &mut candidates);
 
 
     // Only add MF tokens when the preceding token is not a noun...
     if !preceding_is_noun {
-        let lk_idx = try_ok!(adjacent_tokens(graph, p_idx, Direction::Preceeding).find(
-            |idx| {
-                let node = &graph[*idx];
-
-                match feature_value(node.token, TOPO_FIELD_FEATURE) {
-                    Some(field) => field == TOPO_C_FIELD || field == TOPO_LK_BRACKET,
-                    None => false,
-                }
-            },
-        ));
+        let lk_idx = ok_or_drop!(
+            adjacent_tokens(graph, p_idx, Direction::Preceeding).find(|idx| {
+                matches!(
+                    graph[*idx].topological_field(scheme),
+                    Some(TopologicalField::C) | Some(TopologicalField::LK)
+                )
+            }),
+            DropReason::NoClauseBracket
+        );
 
         // Left bracket should not contain any other material...
         let mf_tokens = adjacent_tokens(graph, lk_idx, Direction::Succeeding).take_while(|idx| {
-            match feature_value(&graph[*idx].token, TOPO_FIELD_FEATURE) {
-                Some(field) => field == Field::MF.string_value() || field == TOPO_UNKNOWN_FIELD,
-                None => false,
-            }
+            matches!(
+                graph[*idx].topological_field(scheme),
+                Some(TopologicalField::MF) | Some(TopologicalField::UK)
+            )
         });
 
-        add_tokens(graph, head_idx, mf_tokens, &mut candidates);
+        add_tokens(graph, scheme, head_idx, mf_tokens, &mut candidates);
     }
 
-    Some(candidates)
+    Ok(Ok(candidates))
 }
 
 fn add_tokens<'a, I>(
     graph: &'a DependencyGraph<'a>,
+    scheme: &AnnotationScheme,
     head_idx: NodeIndex,
     tokens: I,
     candidates: &mut Vec<CompetingHead<'a>>,
@@ -507,11 +1071,11 @@ fn add_tokens<'a, I>(
 {
     for idx in tokens {
         let node = &graph[idx];
-        let pos = ok_or_break!(node.token.pos());
+        let pos = ok_or_break!(scheme.tag(node.token));
 
-        if relevant_head_tag(pos) {
+        if relevant_head_tag(scheme, pos) {
             candidates.push(CompetingHead {
-                node: node,
+                node,
                 head: head_idx == idx,
             });
         }
@@ -520,99 +1084,93 @@ fn add_tokens<'a, I>(
 
 fn find_competition_mf<'a>(
     graph: &'a DependencyGraph<'a>,
+    scheme: &AnnotationScheme,
     p_idx: NodeIndex,
     head_idx: NodeIndex,
-) -> Option<Vec<CompetingHead>> {
+) -> Result<Extraction<Vec<CompetingHead<'a>>>> {
+    let topo = &scheme.topological_fields;
     let mut candidates = Vec::new();
 
     for idx in adjacent_tokens(graph, p_idx, Direction::Preceeding) {
         let node = &graph[idx];
-        let pos = ok_or_break!(node.token.pos());
-        let tf = ok_or_break!(feature_value(node.token, TOPO_FIELD_FEATURE));
+        let pos = ok_or_break!(scheme.tag(node.token));
+        let tf = ok_or_break!(feature_value(node.token, &topo.feature));
+        let tf = TopologicalField::from_label(scheme, &tf);
 
-        if FINITE_VERB_TAGS.contains(pos) {
-            let verb_idx = resolve_verb(graph, idx);
+        if scheme.is_finite_verb_tag(pos) {
+            let verb_idx = resolve_verb(graph, scheme, idx);
 
             candidates.push(CompetingHead {
                 node: &graph[verb_idx],
                 head: verb_idx == head_idx,
             });
 
-            return Some(candidates);
+            return Ok(Ok(candidates));
 
-        } else if tf == TOPO_C_FIELD {
+        } else if tf == Some(TopologicalField::C) {
             // Find the finite verb of the clause
-            if let Some(finite_idx) = traverse_c_to_vc(graph, idx) {
-                let verb_idx = resolve_verb(graph, finite_idx);
+            if let Some(finite_idx) = traverse_c_to_vc(graph, scheme, idx) {
+                let verb_idx = resolve_verb(graph, scheme, finite_idx);
 
                 candidates.push(CompetingHead {
                     node: &graph[verb_idx],
                     head: head_idx == verb_idx,
                 });
 
-                return Some(candidates);
+                return Ok(Ok(candidates));
             } else {
                 // C-feld without a head.
-                return None;
+                return Ok(Err(DropReason::CFieldWithoutHead));
             }
-        } else if tf == Field::MF.string_value() || tf == TOPO_UNKNOWN_FIELD {
-            if relevant_head_tag(pos) {
+        } else if tf == Some(TopologicalField::MF) || tf == Some(TopologicalField::UK) {
+            if relevant_head_tag(scheme, pos) {
                 candidates.push(CompetingHead {
-                    node: node,
+                    node,
                     head: head_idx == idx,
                 });
             }
         } else {
-            return None;
+            return Ok(Err(DropReason::UnexpectedField));
         }
     }
 
-    None
+    Ok(Err(DropReason::NoFiniteVerb))
 }
 
-fn traverse_c_to_vc(graph: &DependencyGraph, idx: NodeIndex) -> Option<NodeIndex> {
+fn traverse_c_to_vc(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    idx: NodeIndex,
+) -> Option<NodeIndex> {
     for idx in ancestor_tokens(graph, idx) {
-        let node = &graph[idx];
-        let field = ok_or_break!(feature_value(node.token, TOPO_FIELD_FEATURE));
-
-        if field == TOPO_RK_FIELD {
-            return Some(idx);
-        } else if field != TOPO_C_FIELD {
-            return None;
+        match graph[idx].topological_field(scheme) {
+            Some(TopologicalField::VC) => return Some(idx),
+            Some(TopologicalField::C) => (),
+            _ => return None,
         }
     }
 
     None
 }
 
-fn resolve_verb(graph: &DependencyGraph, verb: NodeIndex) -> NodeIndex {
-    // Look for non-aux.
-    match first_matching_edge(graph, verb, EdgeDirection::Outgoing, |e| {
-        *e == DependencyEdge::Relation(Some(AUXILIARY_RELATION))
-    }) {
-        Some(idx) => resolve_verb(graph, idx),
-        None => verb,
-    }
-}
-
-fn feature_value(token: &Token, feature: &str) -> Option<String> {
-    token
-        .features()
-        .map(Features::as_map)
-        .and_then(|f| f.get(feature))
-        .and_then(|v| v.clone())
+fn node_pos<'a>(scheme: &AnnotationScheme, node: &DependencyNode<'a>) -> Result<&'a str> {
+    scheme.tag(node.token).ok_or(Error::MissingPos {
+        token: node.offset + 1,
+    })
 }
 
-fn field_to_set(field_opt: Option<String>) -> HashSet<Field> {
+fn field_to_set(field_opt: Option<String>) -> HashSet<TopologicalField> {
     if let Some(field) = field_opt {
-        match STRING_FIELD.get(field.as_str()) {
-            Some(field_typed) => hashset!{*field_typed},
-            None => {
+        match field.parse() {
+            Ok(field @ TopologicalField::VF) |
+            Ok(field @ TopologicalField::MF) |
+            Ok(field @ TopologicalField::NF) => hashset!{field},
+            _ => {
                 stderr!("Unknown field: {}", field);
                 process::exit(1);
             }
         }
     } else {
-        hashset!{Field::VF, Field::MF, Field::NF}
+        hashset!{TopologicalField::VF, TopologicalField::MF, TopologicalField::NF}
     }
 }
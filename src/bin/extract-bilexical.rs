@@ -1,14 +1,16 @@
 extern crate conllx;
 
-#[macro_use]
 extern crate syncooc;
 
 extern crate getopts;
 
 extern crate petgraph;
 
+use std::io;
 use std::io::Write;
+use std::path::PathBuf;
 use std::process;
+use std::str::FromStr;
 use std::env::args;
 
 use conllx::Token;
@@ -17,8 +19,8 @@ use getopts::Options;
 
 fn print_usage(program: &str, opts: Options) {
     let brief = format!(
-        "Usage: {} [options] REL [INPUT_FILE] [OUTPUT_FILE]",
-        program
+        "Usage: {} [options] REL [INPUT_FILE...]\n\n{}",
+        program, INPUTS_USAGE
     );
     print!("{}", opts.usage(&brief));
 }
@@ -32,8 +34,54 @@ fn main() {
     let program = args[0].clone();
 
     let mut opts = Options::new();
+    opts.optflag(
+        "c",
+        "count",
+        "print a table of tuple counts instead of one line per tuple",
+    );
+    opts.optflag("F", "filename", "print the input file name of each pair");
+    opts.optopt(
+        "f",
+        "matrix-format",
+        "sparse matrix format: mtx (Matrix Market) or npy (CSR arrays) (default: mtx)",
+        "FORMAT",
+    );
     opts.optflag("h", "help", "print this help menu");
     opts.optflag("l", "lemma", "use lemmas instead of forms");
+    opts.optopt(
+        "m",
+        "min-freq",
+        "only print tuples with at least N occurrences when counting (default: 1)",
+        "N",
+    );
+    opts.optopt(
+        "M",
+        "memory",
+        "memory for counting before writing counts to disk, in MB (default: 1024)",
+        "MB",
+    );
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    opts.optflag("r", "root", "attach root tokens to a virtual root node");
+    opts.optopt("t", "threads", "number of worker threads (default: 1)", "N");
+    opts.optopt(
+        "T",
+        "tmpdir",
+        "directory for temporary count files (default: system temporary directory)",
+        "DIR",
+    );
+    opts.optopt(
+        "x",
+        "matrix",
+        "write a sparse head-dependent matrix to PREFIX.mtx or PREFIX.{indptr,indices,data}.npy, \
+         with tab-separated form-tag vocabularies in PREFIX.rows and PREFIX.cols \
+         (cannot be combined with -c, -F, or -o)",
+        "PREFIX",
+    );
     let matches = or_exit(opts.parse(&args[1..]));
 
     if matches.opt_present("h") {
@@ -41,30 +89,193 @@ fn main() {
         return;
     }
 
-    if matches.free.len() == 0 || matches.free.len() > 3 {
+    if matches.free.is_empty() {
         print_usage(&program, opts);
         process::exit(1);
     }
 
     let relation = &matches.free[0];
 
-    // Read CoNNL-X from stdin or file.
-    let input = or_stdin(matches.free.get(1));
-    let reader = conllx::Reader::new(or_exit(input.buf_read()));
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free[1..]));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
+
+    let pipeline = Pipeline::new(or_exit(matches.opt_get_default("t", 1)))
+        .root(matches.opt_present("r"));
+    let filename = matches.opt_present("F");
+    let lemma = matches.opt_present("l");
+    let min_freq = or_exit(matches.opt_get_default("m", 1));
+
+    if let Some(prefix) = matches.opt_str("x") {
+        for &opt in &["c", "F", "M", "o", "T"] {
+            if matches.opt_present(opt) {
+                stderr!("-{} cannot be used with -x", opt);
+                process::exit(1);
+            }
+        }
+
+        let format = or_exit(matches.opt_get_default("f", MatrixFormat::MatrixMarket));
+
+        let mut matrix = CooccurrenceMatrix::new();
+        or_exit(pipeline.run(
+            reader,
+            |_, graph| {
+                // Vocabulary entries separate the form and the tag by a tab.
+                Ok(bilexical_pairs(graph, relation, lemma)
+                    .into_iter()
+                    .map(|(head, dep)| {
+                        (
+                            format!("{}\t{}", head.0, head.1),
+                            format!("{}\t{}", dep.0, dep.1),
+                        )
+                    })
+                    .collect::<Vec<_>>())
+            },
+            |pairs| {
+                for (head, dep) in or_warn(pairs).unwrap_or_default() {
+                    matrix.add(&head, &dep, 1);
+                }
+
+                Ok(())
+            },
+        ));
 
-    let output = or_stdout(matches.free.get(2));
+        write_matrix(&matrix.to_csr(min_freq), &prefix, format);
+        return;
+    }
+
+    if matches.opt_present("f") {
+        stderr!("-f can only be used with -x");
+        process::exit(1);
+    }
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
     let mut writer = or_exit(output.buf_write());
 
-    for sentence in reader {
-        let sentence = or_exit(sentence);
-        let graph = sentence_to_graph(&sentence, false);
-        print_bilex(&mut writer, &graph, relation, matches.opt_present("l"))
+    let mut counter = if matches.opt_present("c") {
+        let memory: usize = or_exit(matches.opt_get_default("M", 1024));
+        let counter = CooccurrenceCounter::new(memory * 1024 * 1024);
+        Some(match matches.opt_str("T") {
+            Some(temp_dir) => counter.temp_dir(temp_dir),
+            None => counter,
+        })
+    } else {
+        None
+    };
+
+    or_exit(pipeline.run(
+        reader,
+        |sentence, graph| {
+            let source = if filename {
+                Some(sentence.source.as_str())
+            } else {
+                None
+            };
+
+            let mut output = Vec::new();
+            print_bilex(&mut output, source, graph, relation, lemma);
+            Ok(output)
+        },
+        |output| {
+            if let Some(output) = or_warn(output) {
+                match counter {
+                    Some(ref mut counter) => counter.count_lines(&output)?,
+                    None => writer.write_all(&output)?,
+                }
+            }
+
+            Ok(())
+        },
+    ));
+
+    if let Some(counter) = counter {
+        or_exit(counter.write(&mut writer, min_freq));
     }
+
+    or_exit(writer.finish());
 }
 
-fn print_bilex(writer: &mut Write, graph: &DependencyGraph, relation: &str, lemma: bool) {
+/// Sparse matrix output formats.
+#[derive(Clone, Copy)]
+enum MatrixFormat {
+    MatrixMarket,
+    Npy,
+}
+
+impl FromStr for MatrixFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "mtx" => Ok(MatrixFormat::MatrixMarket),
+            "npy" => Ok(MatrixFormat::Npy),
+            _ => Err(format!("unknown matrix format: {}", s)),
+        }
+    }
+}
+
+fn write_matrix(matrix: &CsrMatrix, prefix: &str, format: MatrixFormat) {
+    let write = |suffix: &str, write_fn: &dyn Fn(&mut dyn Write) -> io::Result<()>| {
+        let output = Output::File(PathBuf::from(format!("{}.{}", prefix, suffix)));
+        let mut writer = or_exit(output.buf_write());
+        or_exit(write_fn(&mut writer));
+        or_exit(writer.finish());
+    };
+
+    match format {
+        MatrixFormat::MatrixMarket => write("mtx", &|w| matrix.write_matrix_market(w)),
+        MatrixFormat::Npy => {
+            write("indptr.npy", &|w| matrix.write_indptr_npy(w));
+            write("indices.npy", &|w| matrix.write_indices_npy(w));
+            write("data.npy", &|w| matrix.write_data_npy(w));
+        }
+    }
+
+    write("rows", &|w| write_vocab(w, &matrix.rows));
+    write("cols", &|w| write_vocab(w, &matrix.cols));
+}
+
+fn write_vocab(writer: &mut dyn Write, vocab: &[String]) -> io::Result<()> {
+    for item in vocab {
+        writeln!(writer, "{}", item)?;
+    }
+
+    Ok(())
+}
+
+fn print_bilex(
+    writer: &mut dyn Write,
+    source: Option<&str>,
+    graph: &DependencyGraph,
+    relation: &str,
+    lemma: bool,
+) {
+    for (head, dep) in bilexical_pairs(graph, relation, lemma) {
+        if let Some(source) = source {
+            or_exit(write!(writer, "{} ", source));
+        }
+
+        or_exit(writeln!(writer, "{} {} {} {}", head.0, head.1, dep.0, dep.1));
+    }
+}
+
+/// A word, represented as its form and part-of-speech tag.
+type Word<'a> = (&'a str, &'a str);
+
+/// Extract the (head, dependent) pairs of a relation.
+fn bilexical_pairs<'a>(
+    graph: &'a DependencyGraph,
+    relation: &str,
+    lemma: bool,
+) -> Vec<(Word<'a>, Word<'a>)> {
+    let mut pairs = Vec::new();
+
     for edge in graph.raw_edges() {
-        if edge.weight == DependencyEdge::Relation(Some(relation)) {
+        if edge.weight.label() == Some(relation) {
             let head = graph[edge.source()].token;
             let dep = graph[edge.target()].token;
 
@@ -74,14 +285,9 @@ fn print_bilex(writer: &mut Write, graph: &DependencyGraph, relation: &str, lemm
             let head_pos = ok_or_continue!(head.pos());
             let dep_pos = ok_or_continue!(dep.pos());
 
-            or_exit(writeln!(
-                writer,
-                "{} {} {} {}",
-                head_form,
-                head_pos,
-                dep_form,
-                dep_pos
-            ));
+            pairs.push(((head_form, head_pos), (dep_form, dep_pos)));
         }
     }
+
+    pairs
 }
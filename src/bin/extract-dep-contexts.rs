@@ -0,0 +1,132 @@
+extern crate conllx;
+
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::env::args;
+use std::io::Write;
+
+use conllx::Token;
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] [INPUT_FILE...]\n\n\
+         Extract (word, context) pairs in word2vecf format. The context of a\n\
+         head is dep/REL, the context of a dependent head/REL-1.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn extract_form(token: &Token, lemma: bool) -> Option<&str> {
+    if lemma { token.lemma() } else { token.form() }
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag(
+        "c",
+        "collapse",
+        "collapse prepositions into the relation, e.g. Messer/PP_mit",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag("l", "lemma", "use lemmas instead of forms");
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optopt("t", "threads", "number of worker threads (default: 1)", "N");
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
+
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    let threads = or_exit(matches.opt_get_default("t", 1));
+    let collapse = if matches.opt_present("c") {
+        Some(&scheme)
+    } else {
+        None
+    };
+    let lemma = matches.opt_present("l");
+
+    or_exit(Pipeline::new(threads).run(
+        reader,
+        |_, graph| {
+            let mut output = Vec::new();
+            print_contexts(&mut output, graph, collapse, lemma);
+            Ok(output)
+        },
+        |output| {
+            if let Some(output) = or_warn(output) {
+                writer.write_all(&output)?;
+            }
+
+            Ok(())
+        },
+    ));
+
+    or_exit(writer.finish());
+}
+
+fn print_contexts(
+    writer: &mut dyn Write,
+    graph: &DependencyGraph,
+    collapse: Option<&AnnotationScheme>,
+    lemma: bool,
+) {
+    for context in dependency_contexts(graph, collapse) {
+        let word = ok_or_continue!(extract_form(graph[context.word].token, lemma));
+        let context_word = ok_or_continue!(extract_form(graph[context.context].token, lemma));
+
+        let relation = match context.preposition {
+            Some(preposition) => format!(
+                "{}_{}",
+                context.relation,
+                ok_or_continue!(extract_form(graph[preposition].token, lemma))
+            ),
+            None => context.relation.to_owned(),
+        };
+
+        or_exit(write!(writer, "{} {}/{}", word, context_word, relation));
+
+        if context.inverse {
+            or_exit(write!(writer, "-1"));
+        }
+
+        or_exit(writeln!(writer));
+    }
+}
@@ -0,0 +1,138 @@
+extern crate conllx;
+
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::env::args;
+use std::io::Write;
+use std::process;
+
+use conllx::Token;
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] PATTERN [INPUT_FILE...]\n\n{}",
+        program, INPUTS_USAGE
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn extract_form(token: &Token, lemma: bool) -> Option<&str> {
+    if lemma { token.lemma() } else { token.form() }
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag("F", "filename", "print the input file name of each match");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag("l", "lemma", "use lemmas instead of forms");
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    opts.optflag("r", "root", "attach root tokens to a virtual root node");
+    opts.optopt("t", "threads", "number of worker threads (default: 1)", "N");
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    if matches.free.is_empty() {
+        print_usage(&program, opts);
+        process::exit(1);
+    }
+
+    let pattern = or_exit(Pattern::parse(&matches.free[0]));
+
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free[1..]));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    let threads = or_exit(matches.opt_get_default("t", 1));
+    let filename = matches.opt_present("F");
+    let lemma = matches.opt_present("l");
+
+    or_exit(Pipeline::new(threads).root(matches.opt_present("r")).run(
+        reader,
+        |sentence, graph| {
+            let source = if filename {
+                Some(sentence.source.as_str())
+            } else {
+                None
+            };
+
+            let mut output = Vec::new();
+            print_matches(
+                &mut output,
+                source,
+                &sentence.sent_id(),
+                graph,
+                &pattern,
+                lemma,
+            );
+            Ok(output)
+        },
+        |output| {
+            if let Some(output) = or_warn(output) {
+                writer.write_all(&output)?;
+            }
+
+            Ok(())
+        },
+    ));
+
+    or_exit(writer.finish());
+}
+
+fn print_matches(
+    writer: &mut dyn Write,
+    source: Option<&str>,
+    sent_id: &str,
+    graph: &DependencyGraph,
+    pattern: &Pattern,
+    lemma: bool,
+) {
+    // Print the named nodes, or all nodes when no node is named.
+    let named = pattern.names().iter().any(Option::is_some);
+
+    for m in pattern.matches(graph) {
+        if let Some(source) = source {
+            or_exit(write!(writer, "{} ", source));
+        }
+
+        or_exit(write!(writer, "{}", sent_id));
+
+        for (name, &idx) in pattern.names().iter().zip(m.nodes()) {
+            if named && name.is_none() {
+                continue;
+            }
+
+            let token = graph[idx].token;
+            or_exit(write!(
+                writer,
+                " {} {}",
+                extract_form(token, lemma).unwrap_or("_"),
+                token.pos().unwrap_or("_")
+            ));
+        }
+
+        or_exit(writeln!(writer));
+    }
+}
@@ -1,29 +1,21 @@
 extern crate conllx;
 
-#[macro_use]
 extern crate syncooc;
 
 extern crate getopts;
 
-extern crate petgraph;
-
 use std::io::Write;
-use std::process;
 use std::env::args;
 
-use conllx::{Features, Token};
+use conllx::Token;
 use syncooc::*;
 use getopts::Options;
-use petgraph::EdgeDirection;
-
-static PP_NOUN: &'static str = "PN";
-
-static PP_RELATION: &'static str = "PP";
-
-static TOPO_FIELD_FEATURE: &'static str = "tf";
 
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [options] [INPUT_FILE] [OUTPUT_FILE]", program);
+    let brief = format!(
+        "Usage: {} [options] [INPUT_FILE...]\n\n{}",
+        program, INPUTS_USAGE
+    );
     print!("{}", opts.usage(&brief));
 }
 
@@ -36,8 +28,48 @@ fn main() {
     let program = args[0].clone();
 
     let mut opts = Options::new();
+    opts.optflag(
+        "c",
+        "count",
+        "print a table of tuple counts instead of one line per tuple",
+    );
+    opts.optflag("F", "filename", "print the input file name of each PP");
     opts.optflag("h", "help", "print this help menu");
     opts.optflag("l", "lemma", "use lemmas instead of forms");
+    opts.optopt(
+        "m",
+        "min-freq",
+        "only print tuples with at least N occurrences when counting (default: 1)",
+        "N",
+    );
+    opts.optopt(
+        "M",
+        "memory",
+        "memory for counting before writing counts to disk, in MB (default: 1024)",
+        "MB",
+    );
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optopt("t", "threads", "number of worker threads (default: 1)", "N");
+    opts.optopt(
+        "T",
+        "tmpdir",
+        "directory for temporary count files (default: system temporary directory)",
+        "DIR",
+    );
     let matches = or_exit(opts.parse(&args[1..]));
 
     if matches.opt_present("h") {
@@ -45,76 +77,159 @@ fn main() {
         return;
     }
 
-    if matches.free.len() > 2 {
-        print_usage(&program, opts);
-        process::exit(1);
-    }
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
 
-    // Read CoNNL-X from stdin or file.
-    let input = or_stdin(matches.free.get(0));
-    let reader = conllx::Reader::new(or_exit(input.buf_read()));
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
 
-    let output = or_stdout(matches.free.get(1));
+    let output = or_stdout(matches.opt_str("o").as_ref());
     let mut writer = or_exit(output.buf_write());
 
-    for sentence in reader {
-        let sentence = or_exit(sentence);
-        let graph = sentence_to_graph(&sentence, false);
-        print_pps(&mut writer, &graph, matches.opt_present("l"))
+    let threads = or_exit(matches.opt_get_default("t", 1));
+    let filename = matches.opt_present("F");
+    let lemma = matches.opt_present("l");
+    let min_freq = or_exit(matches.opt_get_default("m", 1));
+
+    let mut counter = if matches.opt_present("c") {
+        let memory: usize = or_exit(matches.opt_get_default("M", 1024));
+        let counter = CooccurrenceCounter::new(memory * 1024 * 1024);
+        Some(match matches.opt_str("T") {
+            Some(temp_dir) => counter.temp_dir(temp_dir),
+            None => counter,
+        })
+    } else {
+        None
+    };
+
+    or_exit(Pipeline::new(threads).run(
+        reader,
+        |sentence, graph| {
+            let source = if filename {
+                Some(sentence.source.as_str())
+            } else {
+                None
+            };
+
+            let mut output = Vec::new();
+            print_pps(
+                &mut output,
+                source,
+                graph,
+                sentence.conllu.as_ref(),
+                &scheme,
+                lemma,
+            );
+            Ok(output)
+        },
+        |output| {
+            if let Some(output) = or_warn(output) {
+                match counter {
+                    Some(ref mut counter) => counter.count_lines(&output)?,
+                    None => writer.write_all(&output)?,
+                }
+            }
+
+            Ok(())
+        },
+    ));
+
+    if let Some(counter) = counter {
+        or_exit(counter.write(&mut writer, min_freq));
     }
+
+    or_exit(writer.finish());
 }
 
-fn print_pps(writer: &mut Write, graph: &DependencyGraph, lemma: bool) {
-    for edge in graph.raw_edges() {
-        if edge.weight == DependencyEdge::Relation(Some(PP_RELATION)) {
-            let head = graph[edge.source()].token;
-            let dep = graph[edge.target()].token;
+fn print_pps(
+    writer: &mut dyn Write,
+    source: Option<&str>,
+    graph: &DependencyGraph,
+    conllu: Option<&ConlluAnnotations>,
+    scheme: &AnnotationScheme,
+    lemma: bool,
+) {
+    let tf_feature = &scheme.topological_fields.feature;
+
+    for pp in prepositional_phrases(graph, scheme) {
+        let head = graph[pp.head].token;
+        let dep = graph[pp.preposition].token;
+        let dep_n = graph[ok_or_continue!(pp.complement)].token;
+
+        let preceding_tag = match adjacent_tokens(graph, pp.preposition, Direction::Preceeding)
+            .next()
+        {
+            Some(idx) => graph[idx].token.pos().unwrap_or("NONE"),
+            None => "NONE",
+        };
+
+        let head_form = ok_or_continue!(extract_form(head, lemma));
+        let dep_form = ok_or_continue!(preposition_form(graph, &pp, conllu, lemma));
+        let dep_n_form = ok_or_continue!(extract_form(dep_n, lemma));
+
+        let head_pos = ok_or_continue!(head.pos());
+        let dep_pos = ok_or_continue!(dep.pos());
+
+        // Universal Dependencies treebanks do not have topological fields.
+        let (head_field, pp_field) = match scheme.pp_analysis {
+            PpAnalysis::FunctionHead => (
+                ok_or_continue!(feature_value(head, tf_feature)),
+                ok_or_continue!(feature_value(dep, tf_feature)),
+            ),
+            PpAnalysis::ContentHead => (
+                feature_value(head, tf_feature).unwrap_or_else(|| "_".to_owned()),
+                feature_value(dep, tf_feature).unwrap_or_else(|| "_".to_owned()),
+            ),
+        };
+
+        if let Some(source) = source {
+            or_exit(write!(writer, "{} ", source));
+        }
 
-            let preceding_tag =
-                match adjacent_tokens(graph, edge.target(), Direction::Preceeding).next() {
-                    Some(idx) => graph[idx].token.pos().unwrap_or("NONE"),
-                    None => "NONE",
-                };
+        or_exit(writeln!(
+            writer,
+            "{} {} {} {} {} {} {} {}",
+            head_form,
+            head_pos,
+            head_field,
+            dep_form,
+            dep_pos,
+            pp_field,
+            dep_n_form,
+            preceding_tag
+        ));
+    }
+}
 
-            let pn_rel = ok_or_continue!(first_matching_edge(
-                graph,
-                edge.target(),
-                EdgeDirection::Outgoing,
-                |e| *e == DependencyEdge::Relation(Some(PP_NOUN)),
-            ));
-
-            let dep_n = graph[pn_rel].token;
-
-            let head_form = ok_or_continue!(extract_form(head, lemma));
-            let dep_form = ok_or_continue!(extract_form(dep, lemma));
-            let dep_n_form = ok_or_continue!(extract_form(dep_n, lemma));
-
-            let head_pos = ok_or_continue!(head.pos());
-            let dep_pos = ok_or_continue!(dep.pos());
-
-            let head_field = ok_or_continue!(feature_value(head, TOPO_FIELD_FEATURE));
-            let pp_field = ok_or_continue!(feature_value(dep, TOPO_FIELD_FEATURE));
-
-            or_exit(writeln!(
-                writer,
-                "{} {} {} {} {} {} {} {}",
-                head_form,
-                head_pos,
-                head_field,
-                dep_form,
-                dep_pos,
-                pp_field,
-                dep_n_form,
-                preceding_tag
-            ));
+/// Get the form of a preposition.
+///
+/// The words of multiword prepositions are joined with underscores. If the
+/// preposition is part of a contraction in CoNLL-U input, such as *zum*
+/// (*zu dem*), the form of the contraction is used.
+fn preposition_form(
+    graph: &DependencyGraph,
+    pp: &PrepositionalPhrase,
+    conllu: Option<&ConlluAnnotations>,
+    lemma: bool,
+) -> Option<String> {
+    let prep_offset = graph[pp.preposition].offset;
+
+    if !lemma && pp.fixed.is_empty() {
+        if let Some(mwt) = conllu.and_then(|c| c.multiword_token(prep_offset + 1)) {
+            return Some(mwt.form.clone());
         }
     }
-}
 
-fn feature_value(token: &Token, feature: &str) -> Option<String> {
-    token
-        .features()
-        .map(Features::as_map)
-        .and_then(|f| f.get(feature))
-        .and_then(|v| v.clone())
+    let mut forms = vec![extract_form(graph[pp.preposition].token, lemma)?];
+    for &idx in &pp.fixed {
+        forms.push(extract_form(graph[idx].token, lemma)?);
+    }
+
+    Some(forms.join("_"))
 }
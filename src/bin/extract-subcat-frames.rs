@@ -0,0 +1,226 @@
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::env::args;
+use std::io::Write;
+
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] [INPUT_FILE...]\n\n\
+         Extract the subcategorization frames of verbs. Each line contains\n\
+         the sentence identifier, the position of the full verb, the verb,\n\
+         its tag, and the relations of its arguments in sentence order,\n\
+         prepositional arguments with their preposition (e.g. OBJP:auf).\n\
+         With -c, frame counts per verb lemma are printed instead.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag(
+        "c",
+        "count",
+        "print frame counts per verb lemma instead of one line per verb",
+    );
+    opts.optflag("F", "filename", "print the input file name of each verb");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag("l", "lemma", "use lemmas instead of forms");
+    opts.optopt(
+        "m",
+        "min-freq",
+        "only print frames with at least N occurrences when counting (default: 1)",
+        "N",
+    );
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "O",
+        "ordered",
+        "count frames with their argument order (default: argument sets)",
+    );
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optopt("t", "threads", "number of worker threads (default: 1)", "N");
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
+
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    let pipeline = Pipeline::new(or_exit(matches.opt_get_default("t", 1)));
+
+    if matches.opt_present("c") {
+        let counts = count_frames(
+            pipeline,
+            reader,
+            &scheme,
+            matches.opt_present("l"),
+            matches.opt_present("O"),
+        );
+        write_counts(
+            &mut writer,
+            &counts,
+            or_exit(matches.opt_get_default("m", 1)),
+        );
+    } else {
+        print_frames(
+            pipeline,
+            reader,
+            &mut writer,
+            &scheme,
+            matches.opt_present("F"),
+            matches.opt_present("l"),
+        );
+    }
+
+    or_exit(writer.finish());
+}
+
+fn print_frames(
+    pipeline: Pipeline,
+    reader: CorpusReader,
+    writer: &mut dyn Write,
+    scheme: &AnnotationScheme,
+    filename: bool,
+    lemma: bool,
+) {
+    or_exit(pipeline.run(
+        reader,
+        |sentence, graph| {
+            let mut output = Vec::new();
+
+            for frame in subcat_frames(graph, scheme) {
+                let verb = graph[frame.verb].token;
+                let form = if lemma { verb.lemma() } else { verb.form() };
+                let form = ok_or_continue!(form);
+                let pos = ok_or_continue!(verb.pos());
+
+                if filename {
+                    write!(output, "{} ", sentence.source)?;
+                }
+
+                writeln!(
+                    output,
+                    "{} {} {} {} {}",
+                    sentence.sent_id(),
+                    graph[frame.verb].offset + 1,
+                    form,
+                    pos,
+                    frame_label(frame.labels(graph, lemma))
+                )?;
+            }
+
+            Ok(output)
+        },
+        |output| {
+            if let Some(output) = or_warn(output) {
+                writer.write_all(&output)?;
+            }
+
+            Ok(())
+        },
+    ));
+}
+
+/// Frame counts per verb lemma.
+type FrameCounts = BTreeMap<String, HashMap<String, usize>>;
+
+fn count_frames(
+    pipeline: Pipeline,
+    reader: CorpusReader,
+    scheme: &AnnotationScheme,
+    lemma: bool,
+    ordered: bool,
+) -> FrameCounts {
+    let mut counts = FrameCounts::new();
+
+    or_exit(pipeline.run(
+        reader,
+        |_, graph| {
+            Ok(subcat_frames(graph, scheme)
+                .into_iter()
+                .filter_map(|frame| {
+                    let verb = graph[frame.verb].token.lemma()?.to_owned();
+
+                    let mut labels = frame.labels(graph, lemma);
+                    if !ordered {
+                        labels.sort();
+                    }
+
+                    Some((verb, frame_label(labels)))
+                })
+                .collect::<Vec<_>>())
+        },
+        |frames| {
+            if let Some(frames) = or_warn(frames) {
+                for (verb, frame) in frames {
+                    *counts.entry(verb).or_default().entry(frame).or_insert(0) += 1;
+                }
+            }
+
+            Ok(())
+        },
+    ));
+
+    counts
+}
+
+fn write_counts(writer: &mut dyn Write, counts: &FrameCounts, min_freq: usize) {
+    for (verb, frames) in counts {
+        let mut frames: Vec<_> = frames
+            .iter()
+            .filter(|&(_, &count)| count >= min_freq)
+            .collect();
+        frames.sort_by_key(|&(frame, &count)| (Reverse(count), frame));
+
+        for (frame, count) in frames {
+            or_exit(writeln!(writer, "{} {} {}", verb, frame, count));
+        }
+    }
+}
+
+fn frame_label(labels: Vec<String>) -> String {
+    if labels.is_empty() {
+        "_".to_owned()
+    } else {
+        labels.join(",")
+    }
+}
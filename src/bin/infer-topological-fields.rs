@@ -0,0 +1,225 @@
+extern crate conllx;
+
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::env::args;
+use std::io::Write;
+use std::process;
+
+use conllx::WriteSentence;
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] [INPUT_FILE...]\n\n\
+         Infer topological fields from part-of-speech tags and dependency\n\
+         structure and write the corpus as CoNLL-X, with the fields in the\n\
+         field feature of the annotation scheme. Only tokens without a field\n\
+         get the inferred field, unless -r is used. With -e, the inferred\n\
+         fields are compared to the fields in the corpus instead. The\n\
+         annotation scheme must have finite verb tags. Sentences with\n\
+         invalid heads are written unchanged.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag(
+        "c",
+        "confusion",
+        "print the confusion counts of gold and inferred fields (with -e)",
+    );
+    opts.optflag(
+        "e",
+        "evaluate",
+        "print the agreement of inferred fields with the fields in the corpus",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag("r", "replace", "replace the fields of tokens that have a field");
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    if matches.opt_present("c") && !matches.opt_present("e") {
+        stderr!("-c can only be used with -e");
+        process::exit(1);
+    }
+
+    if matches.opt_present("r") && matches.opt_present("e") {
+        stderr!("-r cannot be used with -e");
+        process::exit(1);
+    }
+
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
+    if scheme.tags.finite_verbs.is_empty() {
+        stderr!("Topological fields cannot be inferred without finite verb tags");
+        process::exit(1);
+    }
+
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    if matches.opt_present("e") {
+        let agreement = evaluate(reader, &scheme);
+        write_agreement(&mut writer, &agreement, matches.opt_present("c"));
+    } else {
+        let mut conllx_writer = ConllxWriter::new(&mut writer);
+        let replace = matches.opt_present("r");
+        let feature = &scheme.topological_fields.feature;
+
+        for sentence in reader {
+            let mut sentence = or_exit(sentence);
+
+            let fields = match sentence_to_graph(&sentence.sentence, false, false) {
+                Ok(graph) => infer_fields(&graph, &scheme),
+                Err(err) => {
+                    stderr!("Warning: sentence {}: {}", sentence.sent_id(), err);
+                    Vec::new()
+                }
+            };
+
+            for (token, field) in sentence.sentence.iter_mut().zip(fields) {
+                let field = ok_or_continue!(field);
+                if replace || feature_value(token, feature).is_none() {
+                    set_feature_value(token, feature, field.label(&scheme));
+                }
+            }
+
+            or_exit(conllx_writer.write_sentence(&sentence.sentence));
+        }
+    }
+
+    or_exit(writer.finish());
+}
+
+fn evaluate(reader: CorpusReader, scheme: &AnnotationScheme) -> FieldAgreement {
+    let mut agreement = FieldAgreement::new();
+
+    for sentence in reader {
+        let sentence = or_exit(sentence);
+        let graph = match sentence_to_graph(&sentence.sentence, false, false) {
+            Ok(graph) => graph,
+            Err(err) => {
+                stderr!("Warning: sentence {}: {}", sentence.sent_id(), err);
+                continue;
+            }
+        };
+        let fields = infer_fields(&graph, scheme);
+
+        for (idx, inferred) in graph.node_indices().zip(fields) {
+            // Only evaluate tokens with a gold field.
+            let gold = ok_or_continue!(feature_value(
+                graph[idx].token,
+                &scheme.topological_fields.feature
+            ));
+            agreement.add(TopologicalField::from_label(scheme, &gold), inferred);
+        }
+    }
+
+    agreement
+}
+
+fn write_agreement(writer: &mut dyn Write, agreement: &FieldAgreement, confusion: bool) {
+    or_exit(writeln!(
+        writer,
+        "field\tgold\tinferred\tcorrect\tprecision\trecall\tf1"
+    ));
+
+    let mut inferred_tokens = 0;
+    let mut correct_tokens = 0;
+    for &field in TopologicalField::all() {
+        let gold = agreement.gold(field);
+        let inferred = agreement.inferred(field);
+        let correct = agreement.correct(field);
+        inferred_tokens += inferred;
+        correct_tokens += correct;
+
+        if gold == 0 && inferred == 0 {
+            continue;
+        }
+
+        write_row(writer, field.name(), gold, inferred, correct);
+    }
+
+    write_row(
+        writer,
+        "all",
+        agreement.tokens(),
+        inferred_tokens,
+        correct_tokens,
+    );
+
+    if confusion {
+        or_exit(writeln!(writer));
+        or_exit(writeln!(writer, "gold\tinferred\tcount"));
+        for (&(gold, inferred), count) in agreement.confusion() {
+            or_exit(writeln!(
+                writer,
+                "{}\t{}\t{}",
+                field_name(gold),
+                field_name(inferred),
+                count
+            ));
+        }
+    }
+}
+
+fn write_row(writer: &mut dyn Write, name: &str, gold: usize, inferred: usize, correct: usize) {
+    let precision = ratio(correct as f64, inferred as f64);
+    let recall = ratio(correct as f64, gold as f64);
+    let f1 = ratio(2.0 * precision * recall, precision + recall);
+
+    or_exit(writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}",
+        name, gold, inferred, correct, precision, recall, f1
+    ));
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0.0 {
+        0.0
+    } else {
+        numerator / denominator
+    }
+}
+
+fn field_name(field: Option<TopologicalField>) -> &'static str {
+    field.map(|field| field.name()).unwrap_or("_")
+}
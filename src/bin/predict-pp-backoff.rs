@@ -0,0 +1,87 @@
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::env::args;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::process;
+
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] MODEL [INSTANCE_FILE...]\n\n\
+         Score the candidates of extract-ambiguous-pps instances with a\n\
+         backed-off PP attachment model. Every candidate gets a score column,\n\
+         the attachment probability (0 without evidence).",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag("F", "filename", "instances start with the input file name");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag(
+        "p",
+        "position",
+        "instances have the position of the preposition after the sentence identifier",
+    );
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    if matches.free.is_empty() {
+        print_usage(&program, opts);
+        process::exit(1);
+    }
+
+    let model_input = Input::File(PathBuf::from(&matches.free[0]));
+    let model = or_exit(BackoffModel::read(&mut or_exit(model_input.buf_read())));
+
+    let format = InstanceFormat {
+        source: matches.opt_present("F"),
+        position: matches.opt_present("p"),
+        scores: false,
+    };
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+
+    for input in or_exit(inputs_or_stdin(&matches.free[1..])) {
+        let reader = or_exit(input.buf_read());
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = or_exit(line);
+            let mut instance = ok_or_continue!(or_warn(
+                PpInstance::parse(&line, format)
+                    .map_err(|e| format!("{}, line {}: {}", input.name(), line_no + 1, e))
+            ));
+
+            for candidate in &mut instance.candidates {
+                let tuple = AttachmentTuple {
+                    head: &candidate.token.form,
+                    head_pos: &candidate.token.pos,
+                    preposition: &instance.preposition.form,
+                    object: &instance.object.form,
+                };
+
+                let score = model.score(&tuple).map(|(score, _)| score).unwrap_or(0.0);
+                candidate.score = Some(score);
+            }
+
+            or_exit(instance.write(&mut writer));
+        }
+    }
+
+    or_exit(writer.finish());
+}
@@ -0,0 +1,417 @@
+extern crate conllx;
+
+extern crate syncooc;
+
+extern crate getopts;
+
+extern crate petgraph;
+
+use std::collections::HashMap;
+use std::env::args;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process;
+
+use conllx::{Sentence, WriteSentence};
+use getopts::Options;
+use petgraph::graph::NodeIndex;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] PREDICTIONS [INPUT_FILE...]\n\n\
+         Reattach prepositions to predicted heads and write the corpus as\n\
+         CoNLL-X. Each line of PREDICTIONS contains a sentence identifier,\n\
+         the position of the preposition, and the position of its head.\n\
+         With -i, PREDICTIONS contains scored instances with preposition\n\
+         positions (extract-ambiguous-pps -p), the candidate with the\n\
+         highest score becomes the head. With -F, predictions start with\n\
+         the input file name.\n\n\
+         A head is kept when it resolves to the predicted head through a\n\
+         verb chain, since extract-ambiguous-pps replaces auxiliaries by\n\
+         the full verb. Predictions that would introduce a cycle are\n\
+         skipped with a warning.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+/// Input file name (with -F), sentence identifier, and preposition
+/// position of a prediction.
+type PredictionKey = (Option<String>, String, usize);
+
+/// Predicted heads.
+type Predictions = HashMap<PredictionKey, usize>;
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optflag("F", "filename", "predictions start with the input file name");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag("i", "instances", "predictions are scored instances");
+    opts.optopt("o", "output", "write output to FILE", "FILE");
+    opts.optflag(
+        "P",
+        "phead",
+        "rewrite the projective head (PHEAD) instead of the head (HEAD)",
+    );
+    opts.optopt(
+        "S",
+        "scheme",
+        &format!(
+            "annotation scheme for verb chains, a preset ({}) or a TOML file (default: tueba-dz)",
+            PRESETS.join(", ")
+        ),
+        "SCHEME",
+    );
+    opts.optflag(
+        "u",
+        "conllu",
+        "read CoNLL-U (default: CoNLL-U for .conllu files, CoNLL-X otherwise)",
+    );
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    if matches.free.is_empty() {
+        print_usage(&program, opts);
+        process::exit(1);
+    }
+
+    let scheme = or_exit(AnnotationScheme::preset_or_file(
+        &matches.opt_str("S").unwrap_or_else(|| "tueba-dz".to_owned()),
+    ));
+
+    let filename = matches.opt_present("F");
+    let predictions_input = Input::File(PathBuf::from(&matches.free[0]));
+    let mut predictions = if matches.opt_present("i") {
+        read_instance_predictions(&predictions_input, filename)
+    } else {
+        read_predictions(&predictions_input, filename)
+    };
+
+    // Read CoNNL-X or CoNLL-U from stdin or files.
+    let inputs = or_exit(inputs_or_stdin(&matches.free[1..]));
+    let reader = if matches.opt_present("u") {
+        CorpusReader::with_format(&inputs, InputFormat::Conllu)
+    } else {
+        CorpusReader::new(&inputs)
+    };
+
+    let output = or_stdout(matches.opt_str("o").as_ref());
+    let mut writer = or_exit(output.buf_write());
+    let mut conllx_writer = ConllxWriter::new(&mut writer);
+
+    let phead = matches.opt_present("P");
+
+    for sentence in reader {
+        let mut sentence = or_exit(sentence);
+        let source = if filename {
+            Some(sentence.source.clone())
+        } else {
+            None
+        };
+        let sent_id = sentence.sent_id();
+
+        let mut predicted = Vec::new();
+        for position in 1..=sentence.sentence.as_tokens().len() {
+            let key = (source.clone(), sent_id.clone(), position);
+            if let Some(head) = predictions.remove(&key) {
+                predicted.push((position, head));
+            }
+        }
+
+        if !predicted.is_empty() {
+            reattach(&mut sentence.sentence, &sent_id, &predicted, &scheme, phead);
+        }
+
+        or_exit(conllx_writer.write_sentence(&sentence.sentence));
+    }
+
+    or_exit(writer.finish());
+
+    let mut unused: Vec<_> = predictions.keys().collect();
+    unused.sort();
+    for &(ref source, ref sent_id, position) in unused {
+        match *source {
+            Some(ref source) => stderr!(
+                "Warning: sentence {} in {} does not have a token {}",
+                sent_id,
+                source,
+                position
+            ),
+            None => stderr!(
+                "Warning: sentence {} does not have a token {}",
+                sent_id,
+                position
+            ),
+        }
+    }
+}
+
+/// Attach tokens to their predicted heads.
+///
+/// `predicted` contains the (1-based) positions of tokens and their
+/// predicted heads, where head 0 is the root. The current head is kept if
+/// it resolves to the predicted head through a verb chain. Predictions
+/// that attach a token to itself, to a token outside the sentence, or to
+/// one of its descendants are skipped with a warning.
+fn reattach(
+    sentence: &mut Sentence,
+    sent_id: &str,
+    predicted: &[(usize, usize)],
+    scheme: &AnnotationScheme,
+    phead: bool,
+) {
+    let mut heads: Vec<_> = sentence
+        .iter()
+        .map(|token| if phead { token.p_head() } else { token.head() })
+        .collect();
+
+    let mut rewrites = Vec::new();
+
+    {
+        let graph = match sentence_to_graph(sentence, phead, false) {
+            Ok(graph) => graph,
+            Err(err) => {
+                stderr!("Warning: sentence {}: not reattaching: {}", sent_id, err);
+                return;
+            }
+        };
+
+        for &(position, head) in predicted {
+            if head > heads.len() || head == position {
+                stderr!(
+                    "Warning: sentence {}: cannot attach token {} to {}",
+                    sent_id,
+                    position,
+                    head
+                );
+                continue;
+            }
+
+            // The root has no head, so attaching to it cannot form a
+            // cycle.
+            if head == 0 {
+                heads[position - 1] = Some(head);
+                rewrites.push((position, head));
+                continue;
+            }
+
+            // Extraction replaces auxiliaries by the full verb, so the
+            // predicted head can be the full verb of the current head.
+            if let Some(current) = heads[position - 1] {
+                if current > 0 &&
+                    resolve_verb(&graph, scheme, NodeIndex::new(current - 1)).index() == head - 1
+                {
+                    continue;
+                }
+            }
+
+            if is_descendant(&heads, head, position) {
+                stderr!(
+                    "Warning: sentence {}: attaching token {} to {} would form a cycle",
+                    sent_id,
+                    position,
+                    head
+                );
+                continue;
+            }
+
+            heads[position - 1] = Some(head);
+            rewrites.push((position, head));
+        }
+    }
+
+    for (position, head) in rewrites {
+        let token = &mut sentence[position - 1];
+        if phead {
+            token.set_p_head(Some(head));
+        } else {
+            token.set_head(Some(head));
+        }
+    }
+}
+
+/// Returns `true` if the token at `ancestor` dominates the token at
+/// `position`. Both are 1-based positions.
+fn is_descendant(heads: &[Option<usize>], position: usize, ancestor: usize) -> bool {
+    let mut current = position;
+    for _ in 0..heads.len() {
+        current = match heads[current - 1] {
+            Some(head) if head > 0 => head,
+            _ => return false,
+        };
+
+        if current == ancestor {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn read_predictions(input: &Input, source: bool) -> Predictions {
+    let mut predictions = HashMap::new();
+
+    let reader = or_exit(input.buf_read());
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = or_exit(line);
+        let (key, head) = ok_or_continue!(or_warn(
+            parse_prediction(&line, source)
+                .map_err(|e| format!("{}, line {}: {}", input.name(), line_no + 1, e))
+        ));
+        predictions.insert(key, head);
+    }
+
+    predictions
+}
+
+fn parse_prediction(
+    line: &str,
+    source: bool,
+) -> std::result::Result<(PredictionKey, usize), String> {
+    let mut columns: Vec<_> = line.split_whitespace().collect();
+
+    let n_columns = if source { 4 } else { 3 };
+    if columns.len() != n_columns {
+        return Err(format!(
+            "expected {} columns, got {}",
+            n_columns,
+            columns.len()
+        ));
+    }
+
+    let source = if source {
+        Some(columns.remove(0).to_owned())
+    } else {
+        None
+    };
+
+    let position = columns[1]
+        .parse()
+        .map_err(|_| format!("invalid position: {}", columns[1]))?;
+    let head = columns[2]
+        .parse()
+        .map_err(|_| format!("invalid head: {}", columns[2]))?;
+
+    Ok(((source, columns[0].to_owned(), position), head))
+}
+
+fn read_instance_predictions(input: &Input, source: bool) -> Predictions {
+    let format = InstanceFormat {
+        source,
+        position: true,
+        scores: true,
+    };
+
+    let mut predictions = HashMap::new();
+
+    let reader = or_exit(input.buf_read());
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = or_exit(line);
+        let instance = ok_or_continue!(or_warn(
+            PpInstance::parse(&line, format)
+                .map_err(|e| format!("{}, line {}: {}", input.name(), line_no + 1, e))
+        ));
+
+        // The position is always present in this format.
+        let position = instance.position.expect("instance without position");
+        let best = ok_or_continue!(score_ranking(&instance).first().cloned());
+        let head = position as isize + instance.candidates[best].distance;
+
+        predictions.insert((instance.source, instance.sent_id, position), head as usize);
+    }
+
+    predictions
+}
+
+#[cfg(test)]
+mod tests {
+    use conllx::{Sentence, TokenBuilder};
+
+    use syncooc::AnnotationScheme;
+
+    use super::reattach;
+
+    fn sentence() -> Sentence {
+        let tokens = [
+            ("Peter", "NE", 2, "SUBJ"),
+            ("schneidet", "VVFIN", 0, "ROOT"),
+            ("Brot", "NN", 2, "OBJA"),
+            ("mit", "APPR", 3, "PP"),
+            ("Messer", "NN", 4, "PN"),
+        ];
+
+        Sentence::new(
+            tokens
+                .iter()
+                .map(|&(form, pos, head, rel)| {
+                    TokenBuilder::new()
+                        .form(form)
+                        .pos(pos)
+                        .head(head)
+                        .head_rel(rel)
+                        .token()
+                })
+                .collect(),
+        )
+    }
+
+    fn heads(predicted: &[(usize, usize)]) -> Vec<Option<usize>> {
+        let mut sentence = sentence();
+        reattach(
+            &mut sentence,
+            "1",
+            predicted,
+            &AnnotationScheme::default(),
+            false,
+        );
+        sentence.iter().map(|token| token.head()).collect()
+    }
+
+    #[test]
+    fn attach_to_predicted_head() {
+        assert_eq!(
+            heads(&[(4, 2)]),
+            vec![Some(2), Some(0), Some(2), Some(2), Some(4)]
+        );
+    }
+
+    #[test]
+    fn attach_to_root() {
+        assert_eq!(
+            heads(&[(4, 0)]),
+            vec![Some(2), Some(0), Some(2), Some(0), Some(4)]
+        );
+    }
+
+    #[test]
+    fn cycles_are_skipped() {
+        let unchanged = vec![Some(2), Some(0), Some(2), Some(3), Some(4)];
+
+        // Messer is a dependent of mit.
+        assert_eq!(heads(&[(4, 5)]), unchanged);
+
+        // The verb dominates mit, but attaching mit to the root first
+        // breaks the cycle.
+        assert_eq!(heads(&[(2, 4)]), unchanged);
+        assert_eq!(
+            heads(&[(4, 0), (2, 4)]),
+            vec![Some(2), Some(4), Some(2), Some(0), Some(4)]
+        );
+    }
+
+    #[test]
+    fn invalid_heads_are_skipped() {
+        let unchanged = vec![Some(2), Some(0), Some(2), Some(3), Some(4)];
+
+        assert_eq!(heads(&[(4, 4)]), unchanged);
+        assert_eq!(heads(&[(4, 6)]), unchanged);
+    }
+}
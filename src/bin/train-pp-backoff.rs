@@ -0,0 +1,125 @@
+extern crate syncooc;
+
+extern crate getopts;
+
+use std::env::args;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::process;
+
+use getopts::Options;
+use syncooc::*;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [options] MODEL [INSTANCE_FILE...]\n\n\
+         Train a backed-off PP attachment model from extract-ambiguous-pps\n\
+         instances. Every candidate of an instance is counted, together with\n\
+         whether it is the head of the PP. PPs from extract-pps output (-e)\n\
+         are used to estimate the association of heads and prepositions.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    let program = args[0].clone();
+
+    let mut opts = Options::new();
+    opts.optmulti(
+        "e",
+        "extra",
+        "add the PPs in FILE (extract-pps output) as unambiguous attachments",
+        "FILE",
+    );
+    opts.optflag("F", "filename", "instances start with the input file name");
+    opts.optflag("h", "help", "print this help menu");
+    opts.optflag(
+        "p",
+        "position",
+        "instances have the position of the preposition after the sentence identifier",
+    );
+    let matches = or_exit(opts.parse(&args[1..]));
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        return;
+    }
+
+    if matches.free.is_empty() {
+        print_usage(&program, opts);
+        process::exit(1);
+    }
+
+    let format = InstanceFormat {
+        source: matches.opt_present("F"),
+        position: matches.opt_present("p"),
+        scores: false,
+    };
+
+    let mut model = BackoffModel::new();
+
+    for input in or_exit(inputs_or_stdin(&matches.free[1..])) {
+        let reader = or_exit(input.buf_read());
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = or_exit(line);
+            let instance = ok_or_continue!(or_warn(
+                PpInstance::parse(&line, format)
+                    .map_err(|e| format!("{}, line {}: {}", input.name(), line_no + 1, e))
+            ));
+
+            for candidate in &instance.candidates {
+                let tuple = AttachmentTuple {
+                    head: &candidate.token.form,
+                    head_pos: &candidate.token.pos,
+                    preposition: &instance.preposition.form,
+                    object: &instance.object.form,
+                };
+
+                model.add(&tuple, candidate.head);
+            }
+        }
+    }
+
+    for extra in matches.opt_strs("e") {
+        let input = Input::File(PathBuf::from(extra));
+        let reader = or_exit(input.buf_read());
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = or_exit(line);
+            ok_or_continue!(or_warn(
+                add_pp(&mut model, &line)
+                    .map_err(|e| format!("{}, line {}: {}", input.name(), line_no + 1, e))
+            ));
+        }
+    }
+
+    let output = Output::File(PathBuf::from(&matches.free[0]));
+    let mut writer = or_exit(output.buf_write());
+    or_exit(model.write(&mut writer));
+
+    or_exit(writer.finish());
+}
+
+/// Add a PP from extract-pps output as an unambiguous attachment.
+fn add_pp(model: &mut BackoffModel, line: &str) -> std::result::Result<(), String> {
+    let columns: Vec<_> = line.split_whitespace().collect();
+
+    // Skip the file name, when present.
+    let columns = match columns.len() {
+        8 => &columns[..],
+        9 => &columns[1..],
+        n => return Err(format!("expected 8 or 9 columns, got {}", n)),
+    };
+
+    let tuple = AttachmentTuple {
+        head: columns[0],
+        head_pos: columns[1],
+        preposition: columns[3],
+        object: columns[6],
+    };
+
+    model.add_unambiguous(&tuple);
+
+    Ok(())
+}
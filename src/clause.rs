@@ -0,0 +1,414 @@
+use std::fmt;
+
+use petgraph::graph::NodeIndex;
+
+use graph::{ancestor_tokens, DependencyGraph};
+use scheme::AnnotationScheme;
+use topo::{FieldSpan, TopologicalField};
+
+/// Clause types by the position of the finite verb.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ClauseType {
+    /// Verb-first: the finite verb is in the left bracket, the initial
+    /// field is empty.
+    V1,
+
+    /// Verb-second: the finite verb is in the left bracket, preceded by
+    /// the initial field.
+    V2,
+
+    /// Verb-last: the finite verb is in the verb complex.
+    VerbLast,
+}
+
+impl ClauseType {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ClauseType::V1 => "V1",
+            ClauseType::V2 => "V2",
+            ClauseType::VerbLast => "VL",
+        }
+    }
+}
+
+impl fmt::Display for ClauseType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A clause of a sentence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Clause {
+    pub clause_type: ClauseType,
+
+    /// The finite verb of the clause.
+    pub finite_verb: NodeIndex,
+
+    /// The clause that this clause is embedded in.
+    pub parent: Option<usize>,
+
+    /// Tokens of the clause in sentence order, excluding the tokens of
+    /// embedded clauses.
+    pub tokens: Vec<NodeIndex>,
+
+    /// Field spans of the clause tokens, in sentence order.
+    pub fields: Vec<FieldSpan>,
+}
+
+impl Clause {
+    /// Get the tokens of the clause in a field.
+    pub fn field_tokens(&self, field: TopologicalField) -> Vec<NodeIndex> {
+        self.fields
+            .iter()
+            .filter(|span| span.field == field)
+            .flat_map(|span| span.start..span.end)
+            .map(NodeIndex::new)
+            .collect()
+    }
+
+    /// Get the tokens of the left bracket: the finite verb of a V1 or V2
+    /// clause or the complementizer field of a verb-last clause.
+    pub fn left_bracket(&self) -> Vec<NodeIndex> {
+        match self.clause_type {
+            ClauseType::V1 | ClauseType::V2 => self.field_tokens(TopologicalField::LK),
+            ClauseType::VerbLast => self.field_tokens(TopologicalField::C),
+        }
+    }
+
+    /// Get the tokens of the verb complex (the right bracket).
+    pub fn verb_complex(&self) -> Vec<NodeIndex> {
+        self.field_tokens(TopologicalField::VC)
+    }
+}
+
+/// The clauses of a sentence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Clauses {
+    clauses: Vec<Clause>,
+    token_clauses: Vec<Option<usize>>,
+}
+
+impl Clauses {
+    /// Get a clause by its index.
+    pub fn clause(&self, clause: usize) -> &Clause {
+        &self.clauses[clause]
+    }
+
+    /// Get the index of the clause that a token is in.
+    ///
+    /// Returns `None` for tokens that are not in a clause, such as tokens
+    /// of verbless fragments and the virtual root.
+    pub fn clause_of(&self, idx: NodeIndex) -> Option<usize> {
+        self.token_clauses.get(idx.index()).cloned().unwrap_or(None)
+    }
+
+    /// Returns `true` if `clause` is (transitively) embedded in `ancestor`.
+    pub fn is_embedded_in(&self, clause: usize, ancestor: usize) -> bool {
+        let mut current = self.clauses[clause].parent;
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return true;
+            }
+
+            current = self.clauses[parent].parent;
+        }
+
+        false
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Clause> {
+        self.clauses.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.clauses.len()
+    }
+}
+
+/// Split a sentence into clauses.
+///
+/// Every finite verb in the left bracket or the verb complex starts a
+/// clause. A token is in the clause of the closest finite verb that
+/// dominates it, so that clauses nest like the dependency structure.
+/// Tokens that are not dominated by such a verb are not in a clause.
+/// Clauses are ordered by the position of their finite verb.
+pub fn segment_clauses(graph: &DependencyGraph, scheme: &AnnotationScheme) -> Clauses {
+    let fields: Vec<_> = graph
+        .node_indices()
+        .map(|idx| graph[idx].topological_field(scheme))
+        .collect();
+
+    // Find the finite verbs that start a clause.
+    let mut verbs = Vec::new();
+    let mut verb_clauses = vec![None; graph.node_count()];
+    for idx in graph.node_indices() {
+        let node = &graph[idx];
+        let finite = match scheme.tag(node.token) {
+            Some(pos) => !node.is_root() && scheme.is_finite_verb_tag(pos),
+            None => false,
+        };
+
+        if !finite {
+            continue;
+        }
+
+        match fields[idx.index()] {
+            Some(TopologicalField::LK) | Some(TopologicalField::VC) => {
+                verb_clauses[idx.index()] = Some(verbs.len());
+                verbs.push(idx);
+            }
+            _ => (),
+        }
+    }
+
+    let token_clauses: Vec<_> = graph
+        .node_indices()
+        .map(|idx| {
+            if graph[idx].is_root() {
+                return None;
+            }
+
+            // Limit the number of ancestors, in case the graph has a cycle.
+            Some(idx)
+                .into_iter()
+                .chain(ancestor_tokens(graph, idx).take(graph.node_count()))
+                .filter_map(|idx| verb_clauses[idx.index()])
+                .next()
+        })
+        .collect();
+
+    let clauses: Vec<_> = verbs
+        .iter()
+        .enumerate()
+        .map(|(clause, &verb)| {
+            let tokens: Vec<_> = graph
+                .node_indices()
+                .filter(|idx| token_clauses[idx.index()] == Some(clause))
+                .collect();
+
+            let clause_type = if fields[verb.index()] == Some(TopologicalField::VC) {
+                ClauseType::VerbLast
+            } else {
+                ClauseType::V1
+            };
+
+            Clause {
+                clause_type,
+                finite_verb: verb,
+                parent: ancestor_tokens(graph, verb)
+                    .take(graph.node_count())
+                    .filter_map(|idx| verb_clauses[idx.index()])
+                    .next(),
+                fields: clause_field_spans(&tokens, &fields),
+                tokens,
+            }
+        })
+        .collect();
+
+    let mut clauses = Clauses {
+        clauses,
+        token_clauses,
+    };
+
+    // V1 and V2 can only be distinguished when the embedding is known,
+    // since the initial field can consist of an embedded clause.
+    for clause in 0..clauses.len() {
+        if clauses.clauses[clause].clause_type == ClauseType::V1 &&
+            has_initial_field(&clauses, &fields, clause)
+        {
+            clauses.clauses[clause].clause_type = ClauseType::V2;
+        }
+    }
+
+    clauses
+}
+
+/// Returns `true` if the finite verb of a clause is preceded by its initial
+/// field or by a clause that is embedded in it.
+fn has_initial_field(
+    clauses: &Clauses,
+    fields: &[Option<TopologicalField>],
+    clause: usize,
+) -> bool {
+    let verb = clauses.clause(clause).finite_verb;
+
+    (0..verb.index())
+        .map(NodeIndex::new)
+        .any(|idx| match clauses.clause_of(idx) {
+            Some(token_clause) if token_clause == clause => {
+                fields[idx.index()] == Some(TopologicalField::VF)
+            }
+            Some(token_clause) => clauses.is_embedded_in(token_clause, clause),
+            None => false,
+        })
+}
+
+/// Get the field spans of the tokens of a clause.
+fn clause_field_spans(tokens: &[NodeIndex], fields: &[Option<TopologicalField>]) -> Vec<FieldSpan> {
+    let mut spans: Vec<FieldSpan> = Vec::new();
+
+    for &idx in tokens {
+        let field = match fields[idx.index()] {
+            Some(field) => field,
+            None => continue,
+        };
+
+        if let Some(span) = spans.last_mut() {
+            if span.field == field && span.end == idx.index() {
+                span.end += 1;
+                continue;
+            }
+        }
+
+        spans.push(FieldSpan {
+            field,
+            start: idx.index(),
+            end: idx.index() + 1,
+        });
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::{segment_clauses, ClauseType};
+    use graph::sentence_to_graph;
+    use scheme::AnnotationScheme;
+    use test_util::sentence;
+    use topo::TopologicalField;
+
+    fn indices(indices: &[usize]) -> Vec<NodeIndex> {
+        indices.iter().cloned().map(NodeIndex::new).collect()
+    }
+
+    #[test]
+    fn verb_first() {
+        let sentence = sentence(
+            "1  Kommst  kommen  VVFIN  VVFIN  tf:LK  0  ROOT     _  _
+             2  du      du      PPER   PPER   tf:MF  1  SUBJ     _  _
+             3  ?       ?       $.     $.     _      1  -PUNCT-  _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let clauses = segment_clauses(&graph, &AnnotationScheme::default());
+
+        assert_eq!(clauses.len(), 1);
+        let clause = clauses.clause(0);
+        assert_eq!(clause.clause_type, ClauseType::V1);
+        assert_eq!(clause.finite_verb, NodeIndex::new(0));
+        assert_eq!(clause.parent, None);
+        assert_eq!(clause.tokens, indices(&[0, 1, 2]));
+        assert_eq!(clause.left_bracket(), indices(&[0]));
+        assert_eq!(clause.field_tokens(TopologicalField::MF), indices(&[1]));
+    }
+
+    #[test]
+    fn verb_second() {
+        let sentence = sentence(
+            "1  Er        er        PPER   PPER   tf:VF  2  SUBJ  _  _
+             2  hat       haben     VAFIN  VAFIN  tf:LK  0  ROOT  _  _
+             3  ein       ein       ART    ART    tf:MF  4  DET   _  _
+             4  Buch      Buch      NN     NN     tf:MF  5  OBJA  _  _
+             5  gelesen   lesen     VVPP   VVPP   tf:VC  2  AUX   _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let clauses = segment_clauses(&graph, &AnnotationScheme::default());
+
+        // Non-finite verbs do not start a clause.
+        assert_eq!(clauses.len(), 1);
+        let clause = clauses.clause(0);
+        assert_eq!(clause.clause_type, ClauseType::V2);
+        assert_eq!(clause.tokens, indices(&[0, 1, 2, 3, 4]));
+        assert_eq!(clause.left_bracket(), indices(&[1]));
+        assert_eq!(clause.verb_complex(), indices(&[4]));
+    }
+
+    #[test]
+    fn nested_verb_last_clauses() {
+        let sentence = sentence(
+            "1   Glaubst  glauben  VVFIN  VVFIN  tf:LK  0   ROOT     _  _
+             2   du       du       PPER   PPER   tf:MF  1   SUBJ     _  _
+             3   ,        ,        $,     $,     _      1   -PUNCT-  _  _
+             4   dass     dass     KOUS   KOUS   tf:C   6   KONJ     _  _
+             5   er       er       PPER   PPER   tf:MF  6   SUBJ     _  _
+             6   sagt     sagen    VVFIN  VVFIN  tf:VC  1   OBJC     _  _
+             7   ,        ,        $,     $,     _      6   -PUNCT-  _  _
+             8   dass     dass     KOUS   KOUS   tf:C   10  KONJ     _  _
+             9   sie      sie      PPER   PPER   tf:MF  10  SUBJ     _  _
+             10  kommt    kommen   VVFIN  VVFIN  tf:VC  6   OBJC     _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let clauses = segment_clauses(&graph, &AnnotationScheme::default());
+
+        assert_eq!(clauses.len(), 3);
+
+        // A clause that follows the finite verb is not its initial field.
+        let main = clauses.clause(0);
+        assert_eq!(main.clause_type, ClauseType::V1);
+        assert_eq!(main.parent, None);
+        assert_eq!(main.tokens, indices(&[0, 1, 2]));
+
+        let embedded = clauses.clause(1);
+        assert_eq!(embedded.clause_type, ClauseType::VerbLast);
+        assert_eq!(embedded.parent, Some(0));
+        assert_eq!(embedded.tokens, indices(&[3, 4, 5, 6]));
+        assert_eq!(embedded.left_bracket(), indices(&[3]));
+        assert_eq!(embedded.verb_complex(), indices(&[5]));
+
+        let nested = clauses.clause(2);
+        assert_eq!(nested.clause_type, ClauseType::VerbLast);
+        assert_eq!(nested.parent, Some(1));
+        assert_eq!(nested.tokens, indices(&[7, 8, 9]));
+
+        assert!(clauses.is_embedded_in(2, 0));
+        assert!(!clauses.is_embedded_in(0, 2));
+        assert_eq!(clauses.clause_of(NodeIndex::new(8)), Some(2));
+    }
+
+    #[test]
+    fn embedded_clause_is_initial_field() {
+        let sentence = sentence(
+            "1  Dass    dass     KOUS    KOUS    tf:C   3  KONJ   _  _
+             2  er      er       PPER    PPER    tf:MF  3  SUBJ   _  _
+             3  kommt   kommen   VVFIN   VVFIN   tf:VC  4  SUBJC  _  _
+             4  glaube  glauben  VVFIN   VVFIN   tf:LK  0  ROOT   _  _
+             5  ich     ich      PPER    PPER    tf:MF  4  SUBJ   _  _
+             6  nicht   nicht    PTKNEG  PTKNEG  tf:MF  4  ADV    _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let clauses = segment_clauses(&graph, &AnnotationScheme::default());
+
+        // Clauses are ordered by the position of the finite verb.
+        assert_eq!(clauses.len(), 2);
+        assert_eq!(clauses.clause(0).clause_type, ClauseType::VerbLast);
+        assert_eq!(clauses.clause(0).parent, Some(1));
+
+        // The main clause has no initial field tokens of its own, but the
+        // embedded clause is its initial field.
+        let main = clauses.clause(1);
+        assert_eq!(main.clause_type, ClauseType::V2);
+        assert!(main.field_tokens(TopologicalField::VF).is_empty());
+        assert_eq!(main.tokens, indices(&[3, 4, 5]));
+    }
+
+    #[test]
+    fn fragments_are_not_in_a_clause() {
+        let sentence = sentence(
+            "1  Guten   gut    ADJA  ADJA  _  2  ATTR  _  _
+             2  Morgen  Morgen NN    NN    _  0  ROOT  _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, true).unwrap();
+        let clauses = segment_clauses(&graph, &AnnotationScheme::default());
+
+        assert!(clauses.is_empty());
+        assert_eq!(clauses.clause_of(NodeIndex::new(1)), None);
+        assert_eq!(clauses.clause_of(NodeIndex::new(2)), None);
+    }
+}
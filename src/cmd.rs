@@ -1,24 +1,97 @@
 use std::fmt::Display;
+use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::process;
 
+use flate2::bufread::MultiGzDecoder;
+use glob::glob;
+use flate2::write::GzEncoder;
+use xz2::bufread::XzDecoder;
+use xz2::write::XzEncoder;
+
+/// Compression formats that are supported by `Input` and `Output`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression format from a file extension
+    /// (`.gz`, `.xz`, or `.zst`).
+    pub fn from_extension(path: &Path) -> Compression {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("xz") => Compression::Xz,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Detect the compression format from the magic bytes at the start
+    /// of the data.
+    pub fn from_magic(data: &[u8]) -> Compression {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
 pub enum Input {
     Stdin(io::Stdin),
     File(PathBuf),
 }
 
 impl<'a> Input {
-    pub fn buf_read(&'a self) -> io::Result<Box<BufRead + 'a>> {
-        match self {
-            &Input::Stdin(ref stdin) => Result::Ok(Box::new(stdin.lock())),
-            &Input::File(ref path) => Result::Ok(Box::new(BufReader::new(try!(File::open(path))))),
+    /// The name of the input, `-` for standard input.
+    pub fn name(&self) -> String {
+        match *self {
+            Input::Stdin(_) => "-".to_owned(),
+            Input::File(ref path) => path.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Open the input for reading.
+    ///
+    /// Compressed data is decompressed transparently, the compression
+    /// format is detected from the magic bytes of the data.
+    pub fn buf_read(&'a self) -> io::Result<Box<dyn BufRead + 'a>> {
+        match *self {
+            Input::Stdin(ref stdin) => decompress(stdin.lock()),
+            Input::File(ref path) => decompress(BufReader::new(File::open(path)?)),
         }
     }
 }
 
+fn decompress<'a, R>(mut read: R) -> io::Result<Box<dyn BufRead + 'a>>
+where
+    R: BufRead + 'a,
+{
+    let compression = Compression::from_magic(read.fill_buf()?);
+
+    match compression {
+        Compression::None => Result::Ok(Box::new(read)),
+        Compression::Gzip => Result::Ok(Box::new(BufReader::new(MultiGzDecoder::new(read)))),
+        Compression::Xz => Result::Ok(Box::new(BufReader::new(XzDecoder::new_multi_decoder(
+            read,
+        )))),
+        Compression::Zstd => Result::Ok(Box::new(BufReader::new(
+            zstd::Decoder::with_buffer(read)?,
+        ))),
+    }
+}
+
 pub fn or_stdin(filename: Option<&String>) -> Input {
     match filename {
         Some(n) => Input::File(Path::new(n).to_owned()),
@@ -26,18 +99,209 @@ pub fn or_stdin(filename: Option<&String>) -> Input {
     }
 }
 
+/// Usage text describing the inputs and the output of the extraction
+/// programs.
+pub const INPUTS_USAGE: &str =
+    "Inputs are files, directories, or glob patterns, standard input is\n\
+     read when there are no inputs. The output is written to standard\n\
+     output, or to the file given with -o.";
+
+/// Extensions of the files that are read from directories, optionally
+/// followed by a compression extension.
+static CORPUS_EXTENSIONS: &[&str] = &["conll", "conllu", "conllx"];
+
+/// Expand file names, directories, and glob patterns to inputs.
+///
+/// Directories are traversed recursively, files are sorted by name. Only
+/// files with a corpus extension (`.conll`, `.conllu`, or `.conllx`,
+/// optionally followed by `.gz`, `.xz`, or `.zst`) are read from
+/// directories, hidden files and directories are skipped. Returns
+/// standard input when no file names are given.
+pub fn inputs_or_stdin(filenames: &[String]) -> io::Result<Vec<Input>> {
+    if filenames.is_empty() {
+        return Ok(vec![Input::Stdin(io::stdin())]);
+    }
+
+    let mut paths = Vec::new();
+
+    for filename in filenames {
+        let path = Path::new(filename);
+        let n_paths = paths.len();
+
+        if path.exists() {
+            add_path(path, &mut paths)?;
+
+            if paths.len() == n_paths {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no corpus files in directory: {}", filename),
+                ));
+            }
+
+            continue;
+        }
+
+        let entries = glob(filename).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid pattern {}: {}", filename, e),
+            )
+        })?;
+
+        for entry in entries {
+            let entry = entry.map_err(io::Error::from)?;
+            add_path(&entry, &mut paths)?;
+        }
+
+        if paths.len() == n_paths {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no such file: {}", filename),
+            ));
+        }
+    }
+
+    Ok(paths.into_iter().map(Input::File).collect())
+}
+
+fn add_path(path: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_dir() {
+        add_dir(path, paths)
+    } else {
+        paths.push(path.to_owned());
+        Ok(())
+    }
+}
+
+fn add_dir(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if is_hidden(&entry) {
+            continue;
+        }
+
+        if entry.is_dir() {
+            add_dir(&entry, paths)?;
+        } else if is_corpus_file(&entry) {
+            paths.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn is_corpus_file(path: &Path) -> bool {
+    let path = match Compression::from_extension(path) {
+        Compression::None => path.to_owned(),
+        _ => path.with_extension(""),
+    };
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => CORPUS_EXTENSIONS.contains(&ext),
+        None => false,
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.starts_with('.'),
+        None => false,
+    }
+}
+
 pub enum Output {
     Stdout(io::Stdout),
     File(PathBuf),
 }
 
 impl<'a> Output {
-    pub fn buf_write(&'a self) -> io::Result<Box<Write + 'a>> {
-        match self {
-            &Output::Stdout(ref stdout) => Result::Ok(Box::new(BufWriter::new(stdout.lock()))),
-            &Output::File(ref path) => {
-                Result::Ok(Box::new(BufWriter::new(try!(File::create(path)))))
+    /// Open the output for writing.
+    ///
+    /// Files are compressed when their name has the `.gz`, `.xz`, or
+    /// `.zst` extension. `OutputWriter::finish` must be called after
+    /// writing to finalize the compressed stream.
+    pub fn buf_write(&'a self) -> io::Result<OutputWriter<'a>> {
+        let encoder = match *self {
+            Output::Stdout(ref stdout) => Encoder::Plain(Box::new(stdout.lock())),
+            Output::File(ref path) => {
+                let file = File::create(path)?;
+
+                match Compression::from_extension(path) {
+                    Compression::None => Encoder::Plain(Box::new(file)),
+                    Compression::Gzip => {
+                        Encoder::Gzip(GzEncoder::new(file, flate2::Compression::default()))
+                    }
+                    Compression::Xz => Encoder::Xz(XzEncoder::new(file, 6)),
+                    Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(file, 0)?),
+                }
             }
+        };
+
+        Ok(OutputWriter {
+            write: BufWriter::new(encoder),
+        })
+    }
+}
+
+/// A buffered, possibly compressing, writer for an `Output`.
+pub struct OutputWriter<'a> {
+    write: BufWriter<Encoder<'a>>,
+}
+
+impl<'a> OutputWriter<'a> {
+    /// Flush the buffer and finalize the compressed stream.
+    ///
+    /// Errors that occur while finalizing are only reported by this
+    /// method, dropping the writer discards them.
+    pub fn finish(self) -> io::Result<()> {
+        let encoder = self.write.into_inner().map_err(|e| e.into_error())?;
+
+        match encoder {
+            Encoder::Plain(mut write) => write.flush(),
+            Encoder::Gzip(encoder) => encoder.finish()?.flush(),
+            Encoder::Xz(encoder) => encoder.finish()?.flush(),
+            Encoder::Zstd(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl<'a> Write for OutputWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write.flush()
+    }
+}
+
+enum Encoder<'a> {
+    Plain(Box<dyn Write + 'a>),
+    Gzip(GzEncoder<File>),
+    Xz(XzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+impl<'a> Write for Encoder<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Encoder::Plain(ref mut write) => write.write(buf),
+            Encoder::Gzip(ref mut encoder) => encoder.write(buf),
+            Encoder::Xz(ref mut encoder) => encoder.write(buf),
+            Encoder::Zstd(ref mut encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Encoder::Plain(ref mut write) => write.flush(),
+            Encoder::Gzip(ref mut encoder) => encoder.flush(),
+            Encoder::Xz(ref mut encoder) => encoder.flush(),
+            Encoder::Zstd(ref mut encoder) => encoder.flush(),
         }
     }
 }
@@ -55,3 +319,80 @@ pub fn or_exit<T, E: Display>(r: Result<T, E>) -> T {
         process::exit(1)
     })
 }
+
+/// Print a warning for an error and discard it.
+pub fn or_warn<T, E: Display>(r: Result<T, E>) -> Option<T> {
+    match r {
+        Ok(v) => Some(v),
+        Err(e) => {
+            stderr!("Warning: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::{Read, Write};
+
+    use tempfile::tempdir;
+
+    use super::{inputs_or_stdin, Input, Output};
+
+    #[test]
+    fn compressed_output_roundtrip() {
+        let dir = tempdir().unwrap();
+        let data = "Peter schneidet Brot mit dem Messer .\n".repeat(1000);
+
+        for name in &["out.txt", "out.gz", "out.xz", "out.zst"] {
+            let path = dir.path().join(name);
+
+            let output = Output::File(path.clone());
+            let mut writer = output.buf_write().unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+            writer.finish().unwrap();
+
+            let input = Input::File(path);
+            let mut read = String::new();
+            input.buf_read().unwrap().read_to_string(&mut read).unwrap();
+            assert_eq!(read, data, "roundtrip of {}", name);
+        }
+    }
+
+    #[test]
+    fn directories_contain_corpus_files() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+
+        for name in &[
+            "b.conll",
+            "a.conllu.gz",
+            "sub/c.conllx",
+            ".hidden.conll",
+            ".git/d.conll",
+            "README",
+            "e.conll.gz.part",
+            "f.txt.gz",
+        ] {
+            File::create(dir.path().join(name)).unwrap();
+        }
+
+        let args = vec![dir.path().to_string_lossy().into_owned()];
+        let names: Vec<_> = inputs_or_stdin(&args)
+            .unwrap()
+            .iter()
+            .map(|input| input.name()[args[0].len() + 1..].to_owned())
+            .collect();
+        assert_eq!(names, vec!["a.conllu.gz", "b.conll", "sub/c.conllx"]);
+
+        // Files that are given explicitly are always read.
+        let readme = dir.path().join("README").to_string_lossy().into_owned();
+        assert_eq!(inputs_or_stdin(&[readme]).unwrap().len(), 1);
+
+        let empty = dir.path().join(".git").to_string_lossy().into_owned();
+        fs::remove_file(dir.path().join(".git/d.conll")).unwrap();
+        assert!(inputs_or_stdin(&[empty]).is_err());
+    }
+}
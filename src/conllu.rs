@@ -0,0 +1,482 @@
+use std::io::BufRead;
+
+use conllx::{Features, Sentence, Token};
+
+use error::{Error, Result};
+
+/// The ID of a word or an empty node in a CoNLL-U sentence.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TokenId {
+    /// A word, 1-based, 0 is the root.
+    Word(usize),
+
+    /// An empty node that is inserted after the given word.
+    Empty(usize, usize),
+}
+
+/// A dependency from the enhanced dependency graph (DEPS column).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EnhancedDependency {
+    pub head: TokenId,
+    pub relation: String,
+}
+
+/// A multiword token, such as the contraction *zum* of *zu dem*.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MultiwordToken {
+    /// The first word of the token (1-based).
+    pub first: usize,
+
+    /// The last word of the token (1-based, inclusive).
+    pub last: usize,
+
+    pub form: String,
+}
+
+/// An empty node of the enhanced dependency graph.
+#[derive(Clone, Debug)]
+pub struct EmptyNode {
+    pub id: TokenId,
+    pub token: Token,
+    pub deps: Vec<EnhancedDependency>,
+}
+
+/// The annotations of a CoNLL-U sentence that are not represented in
+/// a CoNLL-X `Sentence`.
+#[derive(Clone, Debug, Default)]
+pub struct ConlluAnnotations {
+    /// Comment lines, without the leading `#`.
+    pub comments: Vec<String>,
+
+    pub multiword_tokens: Vec<MultiwordToken>,
+
+    pub empty_nodes: Vec<EmptyNode>,
+
+    /// The enhanced dependencies of each word.
+    pub deps: Vec<Vec<EnhancedDependency>>,
+
+    /// The MISC column of each word.
+    pub misc: Vec<Option<String>>,
+}
+
+impl ConlluAnnotations {
+    /// Look up the value of a `# key = value` comment.
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        self.comments.iter().find_map(|comment| {
+            let mut parts = comment.splitn(2, '=');
+            let comment_key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+
+            if comment_key == key {
+                Some(value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The sentence identifier from the `sent_id` comment.
+    pub fn sent_id(&self) -> Option<&str> {
+        self.metadata("sent_id")
+    }
+
+    /// The sentence text from the `text` comment.
+    pub fn text(&self) -> Option<&str> {
+        self.metadata("text")
+    }
+
+    /// Get the multiword token that the given word (1-based) is part of.
+    pub fn multiword_token(&self, word: usize) -> Option<&MultiwordToken> {
+        self.multiword_tokens
+            .iter()
+            .find(|mwt| word >= mwt.first && word <= mwt.last)
+    }
+}
+
+/// A sentence read from CoNLL-U.
+///
+/// The words are stored as CoNLL-X tokens: UPOS is stored as the coarse
+/// part-of-speech tag, XPOS as the fine-grained tag. Features are
+/// converted to the `key:value` notation that is used by the `Features`
+/// map of CoNLL-X tokens. The remaining annotations are stored in
+/// `annotations`.
+#[derive(Clone, Debug)]
+pub struct ConlluSentence {
+    pub sentence: Sentence,
+    pub annotations: ConlluAnnotations,
+}
+
+/// A reader for CoNLL-U sentences.
+pub struct ConlluReader<R> {
+    read: R,
+    line_no: usize,
+}
+
+impl<R: BufRead> ConlluReader<R> {
+    pub fn new(read: R) -> Self {
+        ConlluReader { read, line_no: 0 }
+    }
+
+    pub fn read_sentence(&mut self) -> Result<Option<ConlluSentence>> {
+        let mut line = String::new();
+        let mut tokens = Vec::new();
+        let mut annotations = ConlluAnnotations::default();
+
+        loop {
+            line.clear();
+
+            if self.read.read_line(&mut line)? == 0 {
+                if tokens.is_empty() && annotations.comments.is_empty() {
+                    return Ok(None);
+                }
+
+                break;
+            }
+
+            self.line_no += 1;
+
+            let line = line.trim_end_matches(&['\n', '\r'][..]);
+
+            if line.trim().is_empty() {
+                if tokens.is_empty() && annotations.comments.is_empty() {
+                    continue;
+                }
+
+                break;
+            }
+
+            if let Some(comment) = line.strip_prefix('#') {
+                annotations.comments.push(comment.trim().to_owned());
+                continue;
+            }
+
+            self.parse_line(line, &mut tokens, &mut annotations)?;
+        }
+
+        Ok(Some(ConlluSentence {
+            sentence: Sentence::new(tokens),
+            annotations,
+        }))
+    }
+
+    fn error(&self, message: String) -> Error {
+        Error::Conllu {
+            line: self.line_no,
+            message,
+        }
+    }
+
+    fn parse_line(
+        &self,
+        line: &str,
+        tokens: &mut Vec<Token>,
+        annotations: &mut ConlluAnnotations,
+    ) -> Result<()> {
+        let fields: Vec<_> = line.split('\t').collect();
+        if fields.len() != 10 {
+            return Err(self.error(format!("expected 10 columns, found {}", fields.len())));
+        }
+
+        let id = fields[0];
+
+        if let Some(range_pos) = id.find('-') {
+            let first = self.parse_number(&id[..range_pos])?;
+            let last = self.parse_number(&id[range_pos + 1..])?;
+            annotations.multiword_tokens.push(MultiwordToken {
+                first,
+                last,
+                form: fields[1].to_owned(),
+            });
+            return Ok(());
+        }
+
+        let token = self.parse_token(&fields)?;
+        let deps = self.parse_deps(fields[8])?;
+
+        match self.parse_id(id)? {
+            TokenId::Word(n) => {
+                if n != tokens.len() + 1 {
+                    return Err(self.error(format!(
+                        "expected word {}, found word {}",
+                        tokens.len() + 1,
+                        n
+                    )));
+                }
+
+                tokens.push(token);
+                annotations.deps.push(deps);
+                annotations.misc.push(parse_string_field(fields[9]));
+            }
+            empty_id => annotations.empty_nodes.push(EmptyNode {
+                id: empty_id,
+                token,
+                deps,
+            }),
+        }
+
+        Ok(())
+    }
+
+    fn parse_token(&self, fields: &[&str]) -> Result<Token> {
+        let mut token = Token::new();
+        token.set_form(parse_string_field(fields[1]));
+        token.set_lemma(parse_string_field(fields[2]));
+        token.set_cpos(parse_string_field(fields[3]));
+        token.set_pos(parse_string_field(fields[4]));
+        token.set_features(parse_string_field(fields[5]).map(|f| convert_features(&f)));
+
+        if let Some(head) = parse_string_field(fields[6]) {
+            token.set_head(Some(self.parse_number(&head)?));
+        }
+        token.set_head_rel(parse_string_field(fields[7]));
+
+        Ok(token)
+    }
+
+    fn parse_deps(&self, field: &str) -> Result<Vec<EnhancedDependency>> {
+        let mut deps = Vec::new();
+
+        let field = match parse_string_field(field) {
+            Some(field) => field,
+            None => return Ok(deps),
+        };
+
+        for dep in field.split('|') {
+            let mut parts = dep.splitn(2, ':');
+            let head = parts.next().unwrap_or("");
+            let relation = parts
+                .next()
+                .ok_or_else(|| self.error(format!("dependency without a relation: {}", dep)))?;
+
+            deps.push(EnhancedDependency {
+                head: self.parse_id(head)?,
+                relation: relation.to_owned(),
+            });
+        }
+
+        Ok(deps)
+    }
+
+    fn parse_id(&self, id: &str) -> Result<TokenId> {
+        match id.find('.') {
+            Some(dot) => Ok(TokenId::Empty(
+                self.parse_number(&id[..dot])?,
+                self.parse_number(&id[dot + 1..])?,
+            )),
+            None => Ok(TokenId::Word(self.parse_number(id)?)),
+        }
+    }
+
+    fn parse_number(&self, number: &str) -> Result<usize> {
+        number
+            .parse()
+            .map_err(|_| self.error(format!("cannot parse as a number: {}", number)))
+    }
+}
+
+impl<R: BufRead> Iterator for ConlluReader<R> {
+    type Item = Result<ConlluSentence>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_sentence() {
+            Ok(None) => None,
+            Ok(Some(sentence)) => Some(Ok(sentence)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Parse a string column, `_` and empty columns are absent.
+fn parse_string_field(field: &str) -> Option<String> {
+    if field.is_empty() || field == "_" {
+        None
+    } else {
+        Some(field.to_owned())
+    }
+}
+
+/// Convert UD features (`Case=Nom|Number=Sing`) to the notation of
+/// CoNLL-X features (`Case:Nom|Number:Sing`).
+fn convert_features(features: &str) -> Features {
+    let converted: Vec<_> = features
+        .split('|')
+        .map(|fv| fv.replacen('=', ":", 1))
+        .collect();
+    Features::from_string(converted.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::{ConlluReader, ConlluSentence, EnhancedDependency, MultiwordToken, TokenId};
+    use error::{Error, Result};
+
+    /// Read CoNLL-U data, where columns are separated by any amount of
+    /// whitespace.
+    fn read(data: &str) -> Result<Vec<ConlluSentence>> {
+        let data: Vec<_> = data
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                if line.starts_with('#') {
+                    line.to_owned()
+                } else {
+                    line.split_whitespace().collect::<Vec<_>>().join("\t")
+                }
+            })
+            .collect();
+
+        ConlluReader::new(BufReader::new(data.join("\n").as_bytes())).collect()
+    }
+
+    #[test]
+    fn comments_and_sent_id() {
+        let sentences = read(
+            "# newdoc id = doc1
+             # sent_id = s1
+             # text = Er schläft.
+             1  Er       er        PRON   PPER   _  2  nsubj  _  _
+             2  schläft  schlafen  VERB   VVFIN  _  0  root   _  SpaceAfter=No
+             3  .        .         PUNCT  $.     _  2  punct  _  _
+
+             1  Ja  ja  INTJ  ITJ  _  0  root  _  _",
+        ).unwrap();
+
+        assert_eq!(sentences.len(), 2);
+
+        let annotations = &sentences[0].annotations;
+        assert_eq!(
+            annotations.comments,
+            vec!["newdoc id = doc1", "sent_id = s1", "text = Er schläft."]
+        );
+        assert_eq!(annotations.sent_id(), Some("s1"));
+        assert_eq!(annotations.text(), Some("Er schläft."));
+        assert_eq!(annotations.metadata("newdoc id"), Some("doc1"));
+        assert_eq!(
+            annotations.misc,
+            vec![None, Some("SpaceAfter=No".to_owned()), None]
+        );
+
+        let token = &sentences[0].sentence[1];
+        assert_eq!(token.form(), Some("schläft"));
+        assert_eq!(token.cpos(), Some("VERB"));
+        assert_eq!(token.pos(), Some("VVFIN"));
+        assert_eq!(token.head(), Some(0));
+        assert_eq!(token.head_rel(), Some("root"));
+
+        assert!(sentences[1].annotations.comments.is_empty());
+        assert_eq!(sentences[1].annotations.sent_id(), None);
+    }
+
+    #[test]
+    fn multiword_tokens() {
+        let sentences = read(
+            "1    Er      er    PRON  PPER   _  2  nsubj  _  _
+             2    geht    gehen VERB  VVFIN  _  0  root   _  _
+             3-4  zum     _     _     _      _  _  _      _  _
+             3    zu      zu    ADP   APPR   _  5  case   _  _
+             4    dem     der   DET   ART    _  5  det    _  _
+             5    Arzt    Arzt  NOUN  NN     _  2  obl    _  _",
+        ).unwrap();
+
+        let sentence = &sentences[0];
+        assert_eq!(sentence.sentence.as_tokens().len(), 5);
+        assert_eq!(sentence.sentence[2].form(), Some("zu"));
+
+        let mwt = MultiwordToken {
+            first: 3,
+            last: 4,
+            form: "zum".to_owned(),
+        };
+        assert_eq!(sentence.annotations.multiword_tokens, vec![mwt.clone()]);
+        assert_eq!(sentence.annotations.multiword_token(4), Some(&mwt));
+        assert_eq!(sentence.annotations.multiword_token(5), None);
+    }
+
+    #[test]
+    fn empty_nodes_and_enhanced_dependencies() {
+        let sentences = read(
+            "1    Er      er       PRON  PPER   _  2  nsubj  2:nsubj|2.1:nsubj  _
+             2    kauft   kaufen   VERB  VVFIN  _  0  root   0:root             _
+             3    Brot    Brot     NOUN  NN     _  2  obj    2:obj              _
+             4    und     und      CCONJ KON    _  5  cc     2.1:cc             _
+             5    Milch   Milch    NOUN  NN     _  3  conj   2.1:obj            _
+             2.1  kauft   kaufen   VERB  VVFIN  _  _  _      2:conj:und         _",
+        ).unwrap();
+
+        let annotations = &sentences[0].annotations;
+        assert_eq!(sentences[0].sentence.as_tokens().len(), 5);
+
+        assert_eq!(
+            annotations.deps[0],
+            vec![
+                EnhancedDependency {
+                    head: TokenId::Word(2),
+                    relation: "nsubj".to_owned(),
+                },
+                EnhancedDependency {
+                    head: TokenId::Empty(2, 1),
+                    relation: "nsubj".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(annotations.deps[1][0].head, TokenId::Word(0));
+
+        assert_eq!(annotations.empty_nodes.len(), 1);
+        let empty = &annotations.empty_nodes[0];
+        assert_eq!(empty.id, TokenId::Empty(2, 1));
+        assert_eq!(empty.token.form(), Some("kauft"));
+        assert_eq!(empty.token.head(), None);
+        assert_eq!(
+            empty.deps,
+            vec![EnhancedDependency {
+                head: TokenId::Word(2),
+                relation: "conj:und".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn features_are_converted() {
+        // Columns are separated by tabs, so that empty columns can be
+        // represented.
+        let data = "1\tEr\ter\tPRON\tPPER\tCase=Nom|Number=Sing\t2\tnsubj\t_\t_\n\
+                    2\tschläft\tschlafen\tVERB\tVVFIN\t_\t0\troot\t_\t_\n\
+                    3\tjetzt\tjetzt\tADV\tADV\t\t2\tadvmod\t\t\n";
+        let sentence = ConlluReader::new(BufReader::new(data.as_bytes()))
+            .read_sentence()
+            .unwrap()
+            .unwrap();
+
+        let features = sentence.sentence[0].features().unwrap();
+        assert_eq!(features.as_str(), "Case:Nom|Number:Sing");
+        assert_eq!(
+            features.as_map().get("Number"),
+            Some(&Some("Sing".to_owned()))
+        );
+
+        assert!(sentence.sentence[1].features().is_none());
+        assert!(sentence.sentence[2].features().is_none());
+        assert!(sentence.annotations.deps[2].is_empty());
+        assert_eq!(sentence.annotations.misc[2], None);
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        for (data, line) in &[
+            ("1  Er  er  PRON  PPER  _  2  nsubj  _", 1),
+            ("1  Er  er  PRON  PPER  _  x  nsubj  _  _", 1),
+            ("# sent_id = s1\n1-x  zum  _  _  _  _  _  _  _  _", 2),
+            ("1  Er  er  PRON  PPER  _  2  nsubj  2  _", 1),
+            ("1  Er  er  PRON  PPER  _  0  root  _  _\n3  Ja  ja  INTJ  ITJ  _  1  dep  _  _", 2),
+        ] {
+            match read(data) {
+                Err(Error::Conllu { line: error_line, .. }) => {
+                    assert_eq!(error_line, *line, "error line of: {}", data)
+                }
+                result => panic!("expected an error for {}, got {:?}", data, result.map(|_| ())),
+            }
+        }
+    }
+}
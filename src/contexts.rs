@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::EdgeDirection;
+
+use graph::{DependencyEdge, DependencyGraph};
+use pp::prepositional_phrases;
+use scheme::{AnnotationScheme, PpAnalysis};
+
+/// A dependency context of a word.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DependencyContext<'a> {
+    /// The word.
+    pub word: NodeIndex,
+
+    /// The context word.
+    pub context: NodeIndex,
+
+    /// The relation between the word and the context word.
+    pub relation: &'a str,
+
+    /// The preposition of a collapsed PP.
+    pub preposition: Option<NodeIndex>,
+
+    /// If `true`, the context word is the head of the word, otherwise
+    /// it is a dependent.
+    pub inverse: bool,
+}
+
+/// Extract the dependency contexts of the words in a graph.
+///
+/// Every relation between a head and a dependent gives two contexts: the
+/// dependent is a context of the head and the head is an inverse context
+/// of the dependent (Levy & Goldberg, 2014). Attachments to the virtual
+/// root and relations without a label are skipped.
+///
+/// If `collapse` is given, prepositional phrases are collapsed using the
+/// annotation scheme: the complement of the preposition becomes a context
+/// of the PP head and vice versa, with the preposition recorded in the
+/// context. The preposition itself does not get contexts from the PP.
+pub fn dependency_contexts<'a>(
+    graph: &DependencyGraph<'a>,
+    collapse: Option<&AnnotationScheme>,
+) -> Vec<DependencyContext<'a>> {
+    // Relations of collapsed PPs, with the complement and preposition.
+    let mut collapsed = HashMap::new();
+    // Relations that are removed by collapsing PPs.
+    let mut removed = HashSet::new();
+
+    if let Some(scheme) = collapse {
+        for pp in prepositional_phrases(graph, scheme) {
+            let complement = ok_or_continue!(pp.complement);
+
+            let (pp_edge, prep_edge) = match scheme.pp_analysis {
+                PpAnalysis::FunctionHead => (
+                    find_edge(graph, pp.head, pp.preposition),
+                    find_edge(graph, pp.preposition, complement),
+                ),
+                PpAnalysis::ContentHead => (
+                    find_edge(graph, pp.head, complement),
+                    find_edge(graph, complement, pp.preposition),
+                ),
+            };
+
+            let pp_edge = ok_or_continue!(pp_edge);
+            let prep_edge = ok_or_continue!(prep_edge);
+
+            collapsed.insert(pp_edge, (complement, pp.preposition));
+            removed.insert(prep_edge);
+        }
+    }
+
+    let mut contexts = Vec::new();
+
+    for edge in graph.edge_references() {
+        if removed.contains(&edge.id()) {
+            continue;
+        }
+
+        let relation = match *edge.weight() {
+            DependencyEdge::Relation(Some(relation)) => relation,
+            _ => continue,
+        };
+
+        match collapsed.get(&edge.id()) {
+            Some(&(complement, preposition)) => push_contexts(
+                &mut contexts,
+                edge.source(),
+                complement,
+                relation,
+                Some(preposition),
+            ),
+            None => push_contexts(&mut contexts, edge.source(), edge.target(), relation, None),
+        }
+    }
+
+    contexts
+}
+
+fn push_contexts<'a>(
+    contexts: &mut Vec<DependencyContext<'a>>,
+    head: NodeIndex,
+    dependent: NodeIndex,
+    relation: &'a str,
+    preposition: Option<NodeIndex>,
+) {
+    contexts.push(DependencyContext {
+        word: head,
+        context: dependent,
+        relation,
+        preposition,
+        inverse: false,
+    });
+
+    contexts.push(DependencyContext {
+        word: dependent,
+        context: head,
+        relation,
+        preposition,
+        inverse: true,
+    });
+}
+
+fn find_edge(graph: &DependencyGraph, head: NodeIndex, dependent: NodeIndex) -> Option<EdgeIndex> {
+    graph
+        .edges_directed(head, EdgeDirection::Outgoing)
+        .find(|e| e.target() == dependent && e.weight().label().is_some())
+        .map(|e| e.id())
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use graph::{sentence_to_graph, DependencyGraph};
+    use scheme::AnnotationScheme;
+    use test_util::sentence;
+
+    use super::dependency_contexts;
+
+    /// Format contexts as `word context/relation`, where inverse contexts
+    /// get the suffix `-1`.
+    fn contexts(graph: &DependencyGraph, collapse: Option<&AnnotationScheme>) -> Vec<String> {
+        let form = |idx: NodeIndex| graph[idx].token.form().unwrap();
+
+        dependency_contexts(graph, collapse)
+            .into_iter()
+            .map(|context| {
+                let relation = match context.preposition {
+                    Some(preposition) => format!("{}_{}", context.relation, form(preposition)),
+                    None => context.relation.to_owned(),
+                };
+
+                format!(
+                    "{} {}/{}{}",
+                    form(context.word),
+                    form(context.context),
+                    relation,
+                    if context.inverse { "-1" } else { "" }
+                )
+            })
+            .collect()
+    }
+
+    static TUEBA: &str = "1  Peter      Peter      NE     NE     _  2  SUBJ
+                          2  schneidet  schneiden  VVFIN  VVFIN  _  0  ROOT
+                          3  Brot       Brot       NN     NN     _  2  OBJA
+                          4  mit        mit        APPR   APPR   _  2  PP
+                          5  dem        der        ART    ART    _  6  DET
+                          6  Messer     Messer     NN     NN     _  4  PN";
+
+    #[test]
+    fn plain_contexts() {
+        let sentence = sentence(TUEBA);
+        let graph = sentence_to_graph(&sentence, false, true).unwrap();
+
+        assert_eq!(
+            contexts(&graph, None),
+            vec![
+                "schneidet Peter/SUBJ",
+                "Peter schneidet/SUBJ-1",
+                "schneidet Brot/OBJA",
+                "Brot schneidet/OBJA-1",
+                "schneidet mit/PP",
+                "mit schneidet/PP-1",
+                "Messer dem/DET",
+                "dem Messer/DET-1",
+                "mit Messer/PN",
+                "Messer mit/PN-1",
+            ]
+        );
+    }
+
+    #[test]
+    fn collapsed_function_head_pps() {
+        let sentence = sentence(TUEBA);
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+
+        assert_eq!(
+            contexts(&graph, Some(&AnnotationScheme::default())),
+            vec![
+                "schneidet Peter/SUBJ",
+                "Peter schneidet/SUBJ-1",
+                "schneidet Brot/OBJA",
+                "Brot schneidet/OBJA-1",
+                "schneidet Messer/PP_mit",
+                "Messer schneidet/PP_mit-1",
+                "Messer dem/DET",
+                "dem Messer/DET-1",
+            ]
+        );
+    }
+
+    #[test]
+    fn collapsed_content_head_pps() {
+        let sentence = sentence(
+            "1  Er       er        PRON   PPER   _  2  nsubj
+             2  wohnt    wohnen    VERB   VVFIN  _  0  root
+             3  in       in        ADP    APPR   _  4  case
+             4  Berlin   Berlin    PROPN  NE     _  2  obl
+             5  mit      mit       ADP    APPR   _  7  case
+             6  seiner   sein      DET    PPOSAT _  7  det
+             7  Familie  Familie   NOUN   NN     _  2  obl",
+        );
+        let graph = sentence_to_graph(&sentence, false, true).unwrap();
+        let ud = AnnotationScheme::preset("ud").unwrap();
+
+        assert_eq!(
+            contexts(&graph, Some(&ud)),
+            vec![
+                "wohnt Er/nsubj",
+                "Er wohnt/nsubj-1",
+                "wohnt Berlin/obl_in",
+                "Berlin wohnt/obl_in-1",
+                "Familie seiner/det",
+                "seiner Familie/det-1",
+                "wohnt Familie/obl_mit",
+                "Familie wohnt/obl_mit-1",
+            ]
+        );
+
+        // Without collapsing, the preposition is a dependent of the noun.
+        let plain = contexts(&graph, None);
+        assert!(plain.contains(&"Berlin in/case".to_owned()));
+        assert!(plain.contains(&"in Berlin/case-1".to_owned()));
+    }
+}
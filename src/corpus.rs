@@ -0,0 +1,178 @@
+use std::io;
+use std::io::{BufRead, Write};
+
+use conllx::{ReadSentence, Reader, Sentence};
+
+use cmd::{is_corpus_file, Input};
+use conllu::{ConlluAnnotations, ConlluReader};
+use error::{Error, Result};
+
+/// Input formats of a corpus.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputFormat {
+    ConllX,
+    Conllu,
+}
+
+impl InputFormat {
+    /// Detect the format of an input from its file name. Files with the
+    /// `.conllu` extension, optionally followed by a compression extension,
+    /// are CoNLL-U, all other inputs are CoNLL-X.
+    pub fn detect(input: &Input) -> InputFormat {
+        let name = input.name();
+        let name = name
+            .trim_end_matches(".gz")
+            .trim_end_matches(".xz")
+            .trim_end_matches(".zst");
+
+        if name.ends_with(".conllu") {
+            InputFormat::Conllu
+        } else {
+            InputFormat::ConllX
+        }
+    }
+}
+
+/// A sentence from a corpus.
+pub struct CorpusSentence {
+    /// The 1-based position of the sentence in the corpus.
+    pub id: usize,
+
+    /// The name of the input that the sentence was read from.
+    pub source: String,
+
+    pub sentence: Sentence,
+
+    /// Additional annotations of CoNLL-U sentences.
+    pub conllu: Option<ConlluAnnotations>,
+}
+
+impl CorpusSentence {
+    /// The sentence identifier. This is the `sent_id` of CoNLL-U
+    /// sentences that have one, and the position in the corpus otherwise.
+    pub fn sent_id(&self) -> String {
+        self.conllu
+            .as_ref()
+            .and_then(ConlluAnnotations::sent_id)
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| self.id.to_string())
+    }
+}
+
+enum SentenceReader<'a> {
+    ConllX(Reader<Box<dyn BufRead + 'a>>),
+    Conllu(ConlluReader<Box<dyn BufRead + 'a>>),
+}
+
+impl<'a> SentenceReader<'a> {
+    fn read_sentence(&mut self) -> Result<Option<(Sentence, Option<ConlluAnnotations>)>> {
+        match *self {
+            SentenceReader::ConllX(ref mut reader) => {
+                Ok(reader.read_sentence()?.map(|sentence| (sentence, None)))
+            }
+            SentenceReader::Conllu(ref mut reader) => Ok(reader
+                .read_sentence()?
+                .map(|sentence| (sentence.sentence, Some(sentence.annotations)))),
+        }
+    }
+}
+
+/// A reader that reads the sentences of several inputs as one corpus.
+///
+/// Sentences are numbered consecutively across inputs.
+pub struct CorpusReader<'a> {
+    inputs: &'a [Input],
+    format: Option<InputFormat>,
+    next_input: usize,
+    reader: Option<(String, SentenceReader<'a>)>,
+    next_id: usize,
+}
+
+impl<'a> CorpusReader<'a> {
+    /// Construct a reader, detecting the format of each input from its
+    /// file name.
+    ///
+    /// A warning is printed for files that do not have a corpus extension,
+    /// since they are often output files that were given as inputs.
+    pub fn new(inputs: &'a [Input]) -> Self {
+        for input in inputs {
+            if let Input::File(ref path) = *input {
+                if !is_corpus_file(path) {
+                    stderr!(
+                        "Warning: {} is not a corpus file, reading it anyway \
+                         (use -o for the output)",
+                        input.name()
+                    );
+                }
+            }
+        }
+
+        CorpusReader {
+            inputs,
+            format: None,
+            next_input: 0,
+            reader: None,
+            next_id: 1,
+        }
+    }
+
+    /// Construct a reader that reads all inputs in the given format.
+    pub fn with_format(inputs: &'a [Input], format: InputFormat) -> Self {
+        CorpusReader {
+            format: Some(format),
+            ..CorpusReader::new(inputs)
+        }
+    }
+
+    fn read_sentence(&mut self) -> Result<Option<CorpusSentence>> {
+        loop {
+            if let Some((ref source, ref mut reader)) = self.reader {
+                let sentence = reader.read_sentence().map_err(|e| Error::Input {
+                    input: source.clone(),
+                    error: Box::new(e),
+                })?;
+
+                if let Some((sentence, conllu)) = sentence {
+                    let id = self.next_id;
+                    self.next_id += 1;
+
+                    return Ok(Some(CorpusSentence {
+                        id,
+                        source: source.clone(),
+                        sentence,
+                        conllu,
+                    }));
+                }
+            }
+
+            let input = match self.inputs.get(self.next_input) {
+                Some(input) => input,
+                None => return Ok(None),
+            };
+            self.next_input += 1;
+
+            let read = input
+                .buf_read()
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", input.name(), e)))?;
+
+            let reader = match self.format.unwrap_or_else(|| InputFormat::detect(input)) {
+                InputFormat::ConllX => SentenceReader::ConllX(Reader::new(read)),
+                InputFormat::Conllu => SentenceReader::Conllu(ConlluReader::new(read)),
+            };
+
+            self.reader = Some((input.name(), reader));
+        }
+    }
+}
+
+impl<'a> Iterator for CorpusReader<'a> {
+    type Item = Result<CorpusSentence>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_sentence() {
+            Ok(None) => None,
+            Ok(Some(sentence)) => Some(Ok(sentence)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
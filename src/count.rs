@@ -0,0 +1,293 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use tempfile::tempfile_in;
+
+use error::Result;
+
+/// Estimate of the memory used by a hash table entry, excluding the
+/// tuple itself.
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Default maximum number of runs that are merged at once.
+const DEFAULT_FAN_IN: usize = 64;
+
+/// Counter for co-occurrence tuples.
+///
+/// Tuples are counted in memory. When the estimated memory use exceeds
+/// the memory limit, the counts are written to a temporary file as a run
+/// that is sorted by tuple. Runs are merged hierarchically: as soon as
+/// there are `fan_in` runs of the same level, they are merged into one
+/// run of the next level, so that the number of open temporary files
+/// stays small. `write` merges the runs and the remaining in-memory
+/// counts into one count table.
+pub struct CooccurrenceCounter {
+    counts: HashMap<String, u64>,
+    fan_in: usize,
+    memory_limit: usize,
+    memory_used: usize,
+    runs: Vec<Vec<File>>,
+    temp_dir: PathBuf,
+}
+
+impl CooccurrenceCounter {
+    /// Construct a counter that uses approximately `memory_limit` bytes
+    /// for in-memory counts. Runs are stored in the system's temporary
+    /// directory.
+    pub fn new(memory_limit: usize) -> Self {
+        CooccurrenceCounter {
+            counts: HashMap::new(),
+            fan_in: DEFAULT_FAN_IN,
+            memory_limit,
+            memory_used: 0,
+            runs: Vec::new(),
+            temp_dir: ::std::env::temp_dir(),
+        }
+    }
+
+    /// Merge at most `fan_in` runs at once.
+    ///
+    /// Panics if `fan_in` is smaller than 2.
+    pub fn fan_in(mut self, fan_in: usize) -> Self {
+        assert!(fan_in >= 2, "fan-in must be at least 2");
+        self.fan_in = fan_in;
+        self
+    }
+
+    /// Store runs in the given directory.
+    pub fn temp_dir<P>(mut self, temp_dir: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.temp_dir = temp_dir.as_ref().to_owned();
+        self
+    }
+
+    /// Count a tuple.
+    pub fn count(&mut self, tuple: &str) -> Result<()> {
+        self.count_n(tuple, 1)
+    }
+
+    /// Add `n` to the count of a tuple.
+    pub fn count_n(&mut self, tuple: &str, n: u64) -> Result<()> {
+        if let Some(count) = self.counts.get_mut(tuple) {
+            *count += n;
+            return Ok(());
+        }
+
+        self.counts.insert(tuple.to_owned(), n);
+        self.memory_used += tuple.len() + ENTRY_OVERHEAD;
+
+        if self.memory_used > self.memory_limit {
+            self.spill()?;
+        }
+
+        Ok(())
+    }
+
+    /// Count every line of the given data as a tuple.
+    pub fn count_lines(&mut self, data: &[u8]) -> Result<()> {
+        for line in String::from_utf8_lossy(data).lines() {
+            self.count(line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the count table.
+    ///
+    /// Each line contains the count and the tuple, separated by a space.
+    /// Lines are sorted by tuple. Tuples with a count lower than
+    /// `min_freq` are not written.
+    pub fn write(mut self, writer: &mut dyn Write, min_freq: u64) -> Result<()> {
+        if self.runs.iter().all(Vec::is_empty) {
+            for (tuple, count) in self.sorted_counts() {
+                if count >= min_freq {
+                    writeln!(writer, "{} {}", count, tuple)?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        self.spill()?;
+
+        // Merge the runs of all levels until they can be merged at once.
+        let mut runs: Vec<_> = self.runs.drain(..).flatten().collect();
+        while runs.len() > self.fan_in {
+            let mut merged = Vec::new();
+            while !runs.is_empty() {
+                let n = self.fan_in.min(runs.len());
+                merged.push(self.merge_to_run(runs.drain(..n).collect())?);
+            }
+            runs = merged;
+        }
+
+        merge_runs(runs, |tuple, count| {
+            if count >= min_freq {
+                writeln!(writer, "{} {}", count, tuple)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn sorted_counts(&mut self) -> Vec<(String, u64)> {
+        let mut counts: Vec<_> = mem::take(&mut self.counts).into_iter().collect();
+        counts.sort_unstable();
+        self.memory_used = 0;
+        counts
+    }
+
+    /// Write the in-memory counts to a run.
+    fn spill(&mut self) -> Result<()> {
+        let mut writer = BufWriter::new(tempfile_in(&self.temp_dir)?);
+
+        for (tuple, count) in self.sorted_counts() {
+            writeln!(writer, "{}\t{}", count, tuple)?;
+        }
+
+        let run = writer.into_inner().map_err(|e| e.into_error())?;
+        self.add_run(0, run)
+    }
+
+    /// Add a run at the given merge level. When a level has `fan_in`
+    /// runs, they are merged into one run of the next level. This bounds
+    /// the number of open runs.
+    fn add_run(&mut self, level: usize, run: File) -> Result<()> {
+        if self.runs.len() <= level {
+            self.runs.resize_with(level + 1, Vec::new);
+        }
+
+        self.runs[level].push(run);
+
+        if self.runs[level].len() >= self.fan_in {
+            let runs = mem::take(&mut self.runs[level]);
+            let merged = self.merge_to_run(runs)?;
+            self.add_run(level + 1, merged)?;
+        }
+
+        Ok(())
+    }
+
+    /// Merge runs into a new run.
+    fn merge_to_run(&self, runs: Vec<File>) -> Result<File> {
+        let mut writer = BufWriter::new(tempfile_in(&self.temp_dir)?);
+        merge_runs(runs, |tuple, count| {
+            writeln!(writer, "{}\t{}", count, tuple)?;
+            Ok(())
+        })?;
+
+        Ok(writer.into_inner().map_err(|e| e.into_error())?)
+    }
+}
+
+/// Merge sorted runs, calling `emit` with the summed count of every tuple
+/// in tuple order.
+fn merge_runs<F>(runs: Vec<File>, mut emit: F) -> Result<()>
+where
+    F: FnMut(&str, u64) -> Result<()>,
+{
+    let mut readers = Vec::with_capacity(runs.len());
+    for mut run in runs {
+        run.seek(SeekFrom::Start(0))?;
+        readers.push(BufReader::new(run));
+    }
+
+    let mut queue = BinaryHeap::new();
+    for (idx, reader) in readers.iter_mut().enumerate() {
+        if let Some((tuple, count)) = read_run_entry(reader)? {
+            queue.push(Reverse((tuple, idx, count)));
+        }
+    }
+
+    let mut current: Option<(String, u64)> = None;
+    while let Some(Reverse((tuple, idx, count))) = queue.pop() {
+        if let Some((next_tuple, next_count)) = read_run_entry(&mut readers[idx])? {
+            queue.push(Reverse((next_tuple, idx, next_count)));
+        }
+
+        match current {
+            Some((ref current_tuple, ref mut current_count)) if *current_tuple == tuple => {
+                *current_count += count;
+                continue;
+            }
+            _ => (),
+        }
+
+        if let Some((current_tuple, current_count)) = current.take() {
+            emit(&current_tuple, current_count)?;
+        }
+
+        current = Some((tuple, count));
+    }
+
+    if let Some((current_tuple, current_count)) = current {
+        emit(&current_tuple, current_count)?;
+    }
+
+    Ok(())
+}
+
+fn read_run_entry(run: &mut dyn BufRead) -> Result<Option<(String, u64)>> {
+    let mut line = String::new();
+    if run.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    let line = line.trim_end_matches('\n');
+    let mut parts = line.splitn(2, '\t');
+    let count = parts.next().and_then(|count| count.parse().ok());
+    let tuple = parts.next();
+
+    match (count, tuple) {
+        (Some(count), Some(tuple)) => Ok(Some((tuple.to_owned(), count))),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed count in temporary file: {}", line),
+        )
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CooccurrenceCounter;
+
+    fn count_table(mut counter: CooccurrenceCounter, min_freq: u64) -> String {
+        for i in 0..500 {
+            let tuple = format!("w{} w{}", i % 37, i % 11);
+            counter.count(&tuple).unwrap();
+        }
+        counter.count_n("w0 w0", 5).unwrap();
+
+        let mut output = Vec::new();
+        counter.write(&mut output, min_freq).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn spilled_counts_equal_in_memory_counts() {
+        let in_memory = count_table(CooccurrenceCounter::new(1 << 30), 1);
+        assert_eq!(in_memory.lines().count(), 37 * 11);
+
+        // Spill after every few tuples and merge hierarchically.
+        for &fan_in in &[2, 3, 64] {
+            let spilled = CooccurrenceCounter::new(256).fan_in(fan_in);
+            assert_eq!(count_table(spilled, 1), in_memory);
+        }
+    }
+
+    #[test]
+    fn spilled_counts_respect_min_freq() {
+        let in_memory = count_table(CooccurrenceCounter::new(1 << 30), 2);
+        let spilled = count_table(CooccurrenceCounter::new(256).fan_in(2), 2);
+        assert_eq!(spilled, in_memory);
+        assert!(spilled.lines().all(|line| line.split(' ').next().unwrap() != "1"));
+    }
+}
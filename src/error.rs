@@ -0,0 +1,116 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+use conllx;
+use toml;
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors of the dependency graph and extraction functions.
+///
+/// Token positions are 1-based, as in the CoNLL-X ID column. Sentence
+/// positions are 1-based positions in the input.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    ConllX(conllx::Error),
+
+    /// Malformed CoNLL-U input.
+    Conllu { line: usize, message: String },
+
+    /// Malformed ambiguous PP instance.
+    Instance(String),
+
+    /// A token has a head that is not in the sentence.
+    InvalidHead { token: usize, head: usize },
+
+    /// A token is its own ancestor.
+    CyclicHead { token: usize },
+
+    /// A token does not have a part-of-speech tag.
+    MissingPos { token: usize },
+
+    /// Malformed attachment rules.
+    Rules(toml::de::Error),
+
+    /// Malformed annotation scheme.
+    Scheme(toml::de::Error),
+
+    /// An error that occurred in a particular sentence.
+    Sentence { sentence: usize, error: Box<Error> },
+
+    /// An error that occurred while reading a particular input.
+    Input { input: String, error: Box<Error> },
+}
+
+impl Error {
+    /// Record the sentence in which the error occurred.
+    pub fn in_sentence(self, sentence: usize) -> Error {
+        Error::Sentence {
+            sentence,
+            error: Box::new(self),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::ConllX(ref err) => write!(f, "cannot read CoNLL-X: {}", err),
+            Error::Conllu { line, ref message } => {
+                write!(f, "cannot read CoNLL-U, line {}: {}", line, message)
+            }
+            Error::Instance(ref message) => write!(f, "cannot read PP instance: {}", message),
+            Error::InvalidHead { token, head } => write!(
+                f,
+                "token {} has head {}, which is not in the sentence",
+                token, head
+            ),
+            Error::CyclicHead { token } => {
+                write!(f, "the heads of token {} form a cycle", token)
+            }
+            Error::MissingPos { token } => {
+                write!(f, "token {} does not have a part-of-speech tag", token)
+            }
+            Error::Rules(ref err) => write!(f, "cannot read attachment rules: {}", err),
+            Error::Scheme(ref err) => write!(f, "cannot read annotation scheme: {}", err),
+            Error::Sentence {
+                sentence,
+                ref error,
+            } => write!(f, "sentence {}: {}", sentence, error),
+            Error::Input {
+                ref input,
+                ref error,
+            } => write!(f, "{}: {}", input, error),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            Error::ConllX(ref err) => Some(err),
+            Error::Rules(ref err) => Some(err),
+            Error::Scheme(ref err) => Some(err),
+            Error::Sentence { ref error, .. } => Some(error.as_ref()),
+            Error::Input { ref error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<conllx::Error> for Error {
+    fn from(err: conllx::Error) -> Self {
+        Error::ConllX(err)
+    }
+}
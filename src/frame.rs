@@ -0,0 +1,317 @@
+use std::collections::HashSet;
+
+use petgraph::graph::{EdgeReference, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::EdgeDirection;
+
+use graph::{first_matching_edge, DependencyEdge, DependencyGraph};
+use pp::preposition_complement;
+use scheme::{AnnotationScheme, PpAnalysis};
+
+/// An argument in a subcategorization frame.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FrameArgument {
+    pub relation: String,
+    pub dependent: NodeIndex,
+
+    /// The preposition of a prepositional argument.
+    pub preposition: Option<NodeIndex>,
+}
+
+impl FrameArgument {
+    /// The label of the argument in a frame: the relation, followed by the
+    /// preposition of a prepositional argument, such as `OBJP:auf`.
+    pub fn label(&self, graph: &DependencyGraph, lemma: bool) -> String {
+        let preposition = self.preposition.and_then(|idx| {
+            let token = graph[idx].token;
+            if lemma {
+                token.lemma()
+            } else {
+                token.form()
+            }
+        });
+
+        match preposition {
+            Some(preposition) => format!("{}:{}", self.relation, preposition.to_lowercase()),
+            None => self.relation.clone(),
+        }
+    }
+}
+
+/// The subcategorization frame of a verb occurrence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubcatFrame {
+    /// The full verb, see `resolve_verb`.
+    pub verb: NodeIndex,
+
+    /// The arguments, in sentence order.
+    pub arguments: Vec<FrameArgument>,
+}
+
+impl SubcatFrame {
+    /// The argument labels in sentence order.
+    pub fn labels(&self, graph: &DependencyGraph, lemma: bool) -> Vec<String> {
+        self.arguments
+            .iter()
+            .map(|argument| argument.label(graph, lemma))
+            .collect()
+    }
+}
+
+/// Find the subcategorization frames of the verbs in a graph.
+///
+/// Verbs are resolved through auxiliaries with `resolve_verb`, the frame
+/// of the full verb contains the arguments of all the verbs of its verb
+/// chain. Clausal complements that are not part of the verb chain have
+/// their own frames (see `AnnotationScheme::is_verb_chain_relation`).
+/// Frames are ordered by the first verb of their chain.
+pub fn subcat_frames(graph: &DependencyGraph, scheme: &AnnotationScheme) -> Vec<SubcatFrame> {
+    let resolved: Vec<_> = graph
+        .node_indices()
+        .map(|idx| resolve_verb(graph, scheme, idx))
+        .collect();
+
+    let mut frames = Vec::new();
+    let mut seen = HashSet::new();
+
+    for idx in graph.node_indices() {
+        let is_verb = match scheme.tag(graph[idx].token) {
+            Some(pos) => !graph[idx].is_root() && scheme.is_verb_tag(pos),
+            None => false,
+        };
+
+        let verb = resolved[idx.index()];
+        if !is_verb || !seen.insert(verb) {
+            continue;
+        }
+
+        let chain: Vec<_> = graph
+            .node_indices()
+            .filter(|&other| resolved[other.index()] == verb)
+            .collect();
+
+        let mut arguments = Vec::new();
+        for &chain_verb in &chain {
+            for edge in graph.edges_directed(chain_verb, EdgeDirection::Outgoing) {
+                let relation = match *edge.weight() {
+                    DependencyEdge::Relation(Some(relation)) => relation,
+                    _ => continue,
+                };
+
+                // Skip the attachments within the chain.
+                if chain.contains(&edge.target()) || !scheme.is_argument_relation(relation) {
+                    continue;
+                }
+
+                arguments.push(FrameArgument {
+                    relation: relation.to_owned(),
+                    dependent: edge.target(),
+                    preposition: preposition(graph, scheme, edge.target()),
+                });
+            }
+        }
+
+        arguments.sort_by_key(|argument| argument.dependent);
+
+        frames.push(SubcatFrame { verb, arguments });
+    }
+
+    frames
+}
+
+/// Resolve a verb to the full verb that it governs through verb chain
+/// relations.
+pub fn resolve_verb(graph: &DependencyGraph, scheme: &AnnotationScheme, verb: NodeIndex) -> NodeIndex {
+    match governed_verb(graph, scheme, verb) {
+        Some(idx) => resolve_verb(graph, scheme, idx),
+        None => verb,
+    }
+}
+
+/// Find the verb that a verb governs in a verb chain: its verb chain
+/// dependent, or its head if it is an auxiliary that is attached to the
+/// verb that it governs.
+fn governed_verb(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    verb: NodeIndex,
+) -> Option<NodeIndex> {
+    let is_auxiliary_dependent = |edge: &EdgeReference<DependencyEdge>| match *edge.weight() {
+        DependencyEdge::Relation(Some(relation)) => {
+            scheme.is_auxiliary_dependent_relation(relation)
+        }
+        _ => false,
+    };
+
+    if let Some(edge) = graph
+        .edges_directed(verb, EdgeDirection::Incoming)
+        .find(|edge| is_auxiliary_dependent(edge))
+    {
+        return Some(edge.source());
+    }
+
+    graph
+        .edges_directed(verb, EdgeDirection::Outgoing)
+        .find(|&edge| is_verb_chain_edge(graph, scheme, edge) && !is_auxiliary_dependent(&edge))
+        .map(|edge| edge.target())
+}
+
+/// Find the verb that is connected to `verb` through a verb chain relation
+/// in the given direction.
+pub(crate) fn chain_verb(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    verb: NodeIndex,
+    direction: EdgeDirection,
+) -> Option<NodeIndex> {
+    graph
+        .edges_directed(verb, direction)
+        .find(|&edge| is_verb_chain_edge(graph, scheme, edge))
+        .map(|edge| match direction {
+            EdgeDirection::Outgoing => edge.target(),
+            EdgeDirection::Incoming => edge.source(),
+        })
+}
+
+/// Returns `true` if the edge attaches a verb to the verb that governs it
+/// in a verb chain.
+pub(crate) fn is_verb_chain_edge(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    edge: EdgeReference<DependencyEdge>,
+) -> bool {
+    match *edge.weight() {
+        DependencyEdge::Relation(Some(relation)) => {
+            scheme.is_verb_chain_relation(relation, scheme.tag(graph[edge.target()].token))
+        }
+        _ => false,
+    }
+}
+
+/// Get the preposition of an argument, if it is prepositional.
+fn preposition(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    dependent: NodeIndex,
+) -> Option<NodeIndex> {
+    match scheme.pp_analysis {
+        PpAnalysis::FunctionHead => {
+            let is_preposition = match scheme.tag(graph[dependent].token) {
+                Some(pos) => scheme.is_preposition_tag(pos),
+                None => false,
+            };
+
+            if is_preposition {
+                preposition_complement(graph, scheme, dependent).map(|_| dependent)
+            } else {
+                None
+            }
+        }
+        PpAnalysis::ContentHead => first_matching_edge(
+            graph,
+            dependent,
+            EdgeDirection::Outgoing,
+            |e| match *e {
+                DependencyEdge::Relation(Some(relation)) => scheme.is_case_relation(relation),
+                _ => false,
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::{resolve_verb, subcat_frames};
+    use graph::sentence_to_graph;
+    use scheme::AnnotationScheme;
+    use test_util::sentence;
+
+    fn frames(data: &str, scheme: &AnnotationScheme) -> Vec<(usize, Vec<String>)> {
+        let sentence = sentence(data);
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let mut frames: Vec<_> = subcat_frames(&graph, scheme)
+            .into_iter()
+            .map(|frame| (frame.verb.index(), frame.labels(&graph, true)))
+            .collect();
+        frames.sort();
+        frames
+    }
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|&l| l.to_owned()).collect()
+    }
+
+    #[test]
+    fn tueba_frames() {
+        let scheme = AnnotationScheme::default();
+        let frames = frames(
+            "1  Peter   Peter   NE     NE     _  2  SUBJ
+             2  hat     haben   VAFIN  VAFIN  _  0  ROOT
+             3  gesagt  sagen   VVPP   VVPP   _  2  AUX
+             4  ,       ,       $,     $,     _  3  -PUNCT-
+             5  dass    dass    KOUS   KOUS   _  8  KONJ
+             6  er      er      PPER   PPER   _  8  SUBJ
+             7  Auf     auf     APPR   APPR   _  8  OBJP
+             8  wartet  warten  VVFIN  VVFIN  _  3  OBJC
+             9  sie     sie     PPER   PPER   _  7  PN",
+            &scheme,
+        );
+
+        assert_eq!(
+            frames,
+            vec![
+                (2, labels(&["SUBJ", "OBJC"])),
+                (7, labels(&["SUBJ", "OBJP:auf"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn tiger_clausal_objects_are_not_verb_chains() {
+        let scheme = AnnotationScheme::preset("tiger").unwrap();
+        let data = "1  Er       er      PPER   PPER   _  2  SB
+                    2  sagt     sagen   VVFIN  VVFIN  _  0  --
+                    3  ,        ,       $,     $,     _  0  --
+                    4  dass     dass    KOUS   KOUS   _  8  CP
+                    5  er       er      PPER   PPER   _  8  SB
+                    6  das      der     ART    ART    _  7  NK
+                    7  Buch     Buch    NN     NN     _  9  OA
+                    8  hat      haben   VAFIN  VAFIN  _  2  OC
+                    9  gelesen  lesen   VVPP   VVPP   _  8  OC";
+
+        assert_eq!(
+            frames(data, &scheme),
+            vec![(1, labels(&["SB", "OC"])), (8, labels(&["SB", "OA"]))]
+        );
+
+        let sentence = sentence(data);
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        assert_eq!(resolve_verb(&graph, &scheme, NodeIndex::new(1)), NodeIndex::new(1));
+        assert_eq!(resolve_verb(&graph, &scheme, NodeIndex::new(7)), NodeIndex::new(8));
+    }
+
+    #[test]
+    fn ud_auxiliaries_are_dependents() {
+        let scheme = AnnotationScheme::preset("ud").unwrap();
+        let data = "1  Er       er      PRON  PPER   _  4  nsubj
+                    2  wird     werden  AUX   VAFIN  _  4  aux
+                    3  es       es      PRON  PPER   _  4  obj
+                    4  gelesen  lesen   VERB  VVPP   _  0  root
+                    5  haben    haben   AUX   VAINF  _  4  aux";
+
+        assert_eq!(frames(data, &scheme), vec![(3, labels(&["nsubj", "obj"]))]);
+
+        let sentence = sentence(data);
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        assert_eq!(
+            resolve_verb(&graph, &scheme, NodeIndex::new(1)),
+            NodeIndex::new(3)
+        );
+        assert_eq!(
+            resolve_verb(&graph, &scheme, NodeIndex::new(4)),
+            NodeIndex::new(3)
+        );
+    }
+}
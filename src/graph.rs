@@ -1,24 +1,83 @@
-use conllx::{Sentence, Token};
+use std::ptr;
+
+use conllx::{Sentence, Token, TokenBuilder};
+
+use error::{Error, Result};
 
 use petgraph::{Directed, EdgeDirection, Graph};
 use petgraph::graph::NodeIndex;
 use petgraph::visit::EdgeRef;
 
+lazy_static! {
+    pub(crate) static ref ROOT_TOKEN: Token = TokenBuilder::new()
+        .form(ROOT_FORM)
+        .lemma(ROOT_FORM)
+        .cpos(ROOT_FORM)
+        .pos(ROOT_FORM)
+        .token();
+}
+
+/// Form, lemma and tags of the virtual root token.
+pub static ROOT_FORM: &str = "ROOT";
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum DependencyEdge<'a> {
     Relation(Option<&'a str>),
+    /// Attachment of a token to the virtual root.
+    Root(Option<&'a str>),
     Precedence,
 }
 
+impl<'a> DependencyEdge<'a> {
+    /// Get the label of a relation or root attachment.
+    pub fn label(&self) -> Option<&'a str> {
+        match *self {
+            DependencyEdge::Relation(label) | DependencyEdge::Root(label) => label,
+            DependencyEdge::Precedence => None,
+        }
+    }
+}
+
+/// A node of a dependency graph.
+///
+/// The index of a node is the offset of its token in the sentence. The
+/// virtual root follows the tokens, its index and offset are the length
+/// of the sentence.
 #[derive(Debug)]
 pub struct DependencyNode<'a> {
     pub token: &'a Token,
     pub offset: usize,
 }
 
+impl<'a> DependencyNode<'a> {
+    /// Returns `true` if this is the virtual root node.
+    pub fn is_root(&self) -> bool {
+        ptr::eq(self.token, &*ROOT_TOKEN)
+    }
+}
+
 pub type DependencyGraph<'a> = Graph<DependencyNode<'a>, DependencyEdge<'a>, Directed>;
 
-pub fn sentence_to_graph(sentence: &Sentence, projective: bool) -> DependencyGraph {
+/// Convert a sentence to a dependency graph.
+///
+/// The graph contains a node for every token, with relation edges from
+/// heads to dependents and precedence edges between adjacent tokens. If
+/// `projective` is `true`, the projective (P) head layer is used.
+///
+/// The node of the token at offset *i* has index *i*, see
+/// `DependencyNode`. If `root` is `true`, a virtual root node is added
+/// after the tokens, with the length of the sentence as its offset. Tokens
+/// with head 0 are attached to this node through a `Root` edge. The
+/// virtual root is not connected through precedence edges. Otherwise,
+/// root attachments are not represented in the graph.
+///
+/// Returns an error if a token has a head that is not in the sentence,
+/// or if the heads form a cycle.
+pub fn sentence_to_graph(
+    sentence: &Sentence,
+    projective: bool,
+    root: bool,
+) -> Result<DependencyGraph<'_>> {
     let mut g = Graph::new();
 
     let nodes: Vec<_> = sentence
@@ -26,12 +85,23 @@ pub fn sentence_to_graph(sentence: &Sentence, projective: bool) -> DependencyGra
         .enumerate()
         .map(|(offset, token)| {
             g.add_node(DependencyNode {
-                token: token,
-                offset: offset,
+                token,
+                offset,
             })
         })
         .collect();
 
+    let root_node = if root {
+        Some(g.add_node(DependencyNode {
+            token: &ROOT_TOKEN,
+            offset: nodes.len(),
+        }))
+    } else {
+        None
+    };
+
+    let mut heads = Vec::with_capacity(nodes.len());
+
     for (idx, token) in sentence.iter().enumerate() {
         if idx > 0 {
             g.add_edge(nodes[idx - 1], nodes[idx], DependencyEdge::Precedence);
@@ -49,14 +119,79 @@ pub fn sentence_to_graph(sentence: &Sentence, projective: bool) -> DependencyGra
             token.head_rel()
         };
 
-        if let Some(head) = head {
-            if head != 0 {
-                g.add_edge(nodes[head - 1], nodes[idx], DependencyEdge::Relation(rel));
+        match head {
+            Some(0) => {
+                if let Some(root_node) = root_node {
+                    g.add_edge(root_node, nodes[idx], DependencyEdge::Root(rel));
+                }
+            }
+            Some(head) => {
+                let head_node = nodes.get(head - 1).ok_or(Error::InvalidHead {
+                    token: idx + 1,
+                    head,
+                })?;
+                g.add_edge(*head_node, nodes[idx], DependencyEdge::Relation(rel));
+            }
+            None => (),
+        }
+
+        heads.push(head);
+    }
+
+    check_acyclic(&heads)?;
+
+    Ok(g)
+}
+
+/// Check that the heads do not form a cycle, so that walks towards the
+/// root, such as `ancestor_tokens`, terminate.
+fn check_acyclic(heads: &[Option<usize>]) -> Result<()> {
+    for token in 0..heads.len() {
+        let mut current = token;
+        for _ in 0..heads.len() {
+            current = match heads[current] {
+                Some(head) if head > 0 => head - 1,
+                _ => break,
+            };
+
+            if current == token {
+                return Err(Error::CyclicHead { token: token + 1 });
             }
         }
     }
 
-    g
+    Ok(())
+}
+
+/// Find the virtual root node of a graph.
+///
+/// Returns `None` if the graph was constructed without a virtual root.
+pub fn virtual_root(graph: &DependencyGraph) -> Option<NodeIndex> {
+    graph
+        .node_indices()
+        .rev()
+        .find(|&idx| graph[idx].is_root())
+}
+
+/// Find the roots of a graph.
+///
+/// If the graph has a virtual root, the tokens that are attached to the
+/// virtual root are returned. Otherwise, the tokens that do not have a
+/// head are returned. In the latter case, root tokens cannot be told apart
+/// from tokens that are not attached.
+pub fn root_tokens(graph: &DependencyGraph) -> Vec<NodeIndex> {
+    match virtual_root(graph) {
+        Some(root) => graph
+            .edges_directed(root, EdgeDirection::Outgoing)
+            .map(|e| e.target())
+            .collect(),
+        None => graph
+            .node_indices()
+            .filter(|&idx| {
+                first_matching_edge(graph, idx, EdgeDirection::Incoming, is_relation).is_none()
+            })
+            .collect(),
+    }
 }
 
 pub fn first_matching_edge<F>(
@@ -71,7 +206,10 @@ where
     graph
         .edges_directed(index, direction)
         .find(|edge_ref| predicate(edge_ref.weight()))
-        .map(|edge_ref| edge_ref.target())
+        .map(|edge_ref| match direction {
+            EdgeDirection::Outgoing => edge_ref.target(),
+            EdgeDirection::Incoming => edge_ref.source(),
+        })
 }
 
 pub enum Direction {
@@ -85,9 +223,9 @@ pub fn adjacent_tokens<'a>(
     direction: Direction,
 ) -> AdjacentTokens<'a> {
     AdjacentTokens {
-        graph: graph,
+        graph,
         current: index,
-        direction: direction,
+        direction,
     }
 }
 
@@ -120,7 +258,7 @@ impl<'a> Iterator for AdjacentTokens<'a> {
 
 pub fn ancestor_tokens<'a>(graph: &'a DependencyGraph<'a>, index: NodeIndex) -> AncestorTokens<'a> {
     AncestorTokens {
-        graph: graph,
+        graph,
         current: index,
     }
 }
@@ -151,7 +289,53 @@ impl<'a> Iterator for AncestorTokens<'a> {
 
 fn is_relation(e: &DependencyEdge) -> bool {
     match *e {
-        DependencyEdge::Relation(_) => true,
+        DependencyEdge::Relation(_) | DependencyEdge::Root(_) => true,
         DependencyEdge::Precedence => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::{sentence_to_graph, virtual_root, DependencyEdge};
+    use error::Error;
+    use test_util::sentence;
+
+    #[test]
+    fn node_indices_are_offsets() {
+        let sentence = sentence(
+            "1  Peter      Peter      NE     NE     _  2  SUBJ
+             2  schneidet  schneiden  VVFIN  VVFIN  _  0  ROOT
+             3  Brot       Brot       NN     NN     _  2  OBJA",
+        );
+        let graph = sentence_to_graph(&sentence, false, true).unwrap();
+
+        for idx in graph.node_indices() {
+            assert_eq!(graph[idx].offset, idx.index());
+        }
+
+        let root = virtual_root(&graph).unwrap();
+        assert_eq!(root, NodeIndex::new(3));
+        assert!(graph[root].is_root());
+        assert_eq!(
+            graph.find_edge(root, NodeIndex::new(1)).map(|e| &graph[e]),
+            Some(&DependencyEdge::Root(Some("ROOT")))
+        );
+    }
+
+    #[test]
+    fn cyclic_heads_are_rejected() {
+        let sentence = sentence(
+            "1  Peter      Peter      NE     NE     _  2  SUBJ
+             2  schneidet  schneiden  VVFIN  VVFIN  _  3  ROOT
+             3  Brot       Brot       NN     NN     _  2  OBJA
+             4  .          .          $.     $.     _  4  -PUNCT-",
+        );
+
+        match sentence_to_graph(&sentence, false, false) {
+            Err(Error::CyclicHead { token }) => assert_eq!(token, 2),
+            result => panic!("expected a cycle error, got: {:?}", result.map(|_| ())),
+        }
+    }
+}
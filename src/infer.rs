@@ -0,0 +1,496 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::EdgeDirection;
+
+use frame::{chain_verb, is_verb_chain_edge};
+use graph::{ancestor_tokens, DependencyEdge, DependencyGraph};
+use scheme::AnnotationScheme;
+use topo::TopologicalField;
+
+/// Infer the topological fields of the tokens of a sentence.
+///
+/// Fields are inferred from the part-of-speech tags of the annotation
+/// scheme, the dependency structure and the placement of the finite verb.
+/// Since the finite verb is needed to find clauses, no fields are inferred
+/// for schemes without finite verb tags:
+///
+/// * Every finite verb starts a clause, which consists of the tokens that
+///   are dominated by the verb (or by the verb that it is an auxiliary of)
+///   and not by another finite verb.
+/// * A clause is verb-last if its finite verb is preceded by a
+///   complementizer, a relative pronoun, or (in embedded clauses) an
+///   interrogative. Otherwise, it is verb-first or verb-second.
+/// * The finite verb of a verb-first or verb-second clause is the left
+///   bracket, the tokens that precede it the initial field. In verb-last
+///   clauses, the tokens up to the complementizer or relative phrase form
+///   the C field.
+/// * The other verbs of the verb chain and their particles form the verb
+///   complex. Tokens between the left bracket and the verb complex are in
+///   the middle field, tokens after the verb complex in the final field.
+///   If a clause does not have a verb complex, all tokens after the left
+///   bracket are in the middle field.
+///
+/// Coordinating conjunctions at the start of a clause, or that precede a
+/// clause that they govern, are assigned to KOORD, punctuation and tokens
+/// outside clauses do not get a field. Left dislocation and PARORD are not
+/// inferred.
+///
+/// The returned vector contains the field of every token, in sentence order.
+pub fn infer_fields(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+) -> Vec<Option<TopologicalField>> {
+    let tokens: Vec<_> = graph
+        .node_indices()
+        .filter(|&idx| !graph[idx].is_root())
+        .collect();
+
+    let mut fields = vec![None; tokens.len()];
+
+    // Find clauses, keyed by the head of their verb chain.
+    let mut clause_heads = HashMap::new();
+    let mut clauses = Vec::new();
+    for &idx in &tokens {
+        if !has_tag(graph, scheme, idx, |tag| scheme.is_finite_verb_tag(tag)) {
+            continue;
+        }
+
+        let head = chain_head(graph, scheme, idx);
+        if let Entry::Vacant(entry) = clause_heads.entry(head) {
+            entry.insert(clauses.len());
+            clauses.push((head, idx));
+        }
+    }
+
+    // Assign every token to the clause of the closest dominating chain head.
+    let token_clauses: Vec<_> = tokens
+        .iter()
+        .map(|&idx| {
+            Some(idx)
+                .into_iter()
+                .chain(ancestor_tokens(graph, idx).take(graph.node_count()))
+                .filter_map(|idx| clause_heads.get(&idx).cloned())
+                .next()
+        })
+        .collect();
+
+    let parents: Vec<_> = clauses
+        .iter()
+        .map(|&(head, _)| {
+            ancestor_tokens(graph, head)
+                .take(graph.node_count())
+                .filter_map(|idx| clause_heads.get(&idx).cloned())
+                .next()
+        })
+        .collect();
+
+    // Coordinating conjunctions that are attached outside the clause
+    // that they coordinate, such as KON in TüBa-D/Z, precede that clause.
+    for &idx in &tokens {
+        if !has_tag(graph, scheme, idx, |tag| scheme.is_coordination_tag(tag)) {
+            continue;
+        }
+
+        let precedes_clause = graph
+            .edges_directed(idx, EdgeDirection::Outgoing)
+            .filter(|edge| is_relation_edge(edge.weight()))
+            .filter_map(|edge| clause_heads.get(&edge.target()).cloned())
+            .any(|clause| {
+                token_clauses[idx.index()] != Some(clause) &&
+                    tokens
+                        .iter()
+                        .filter(|t| token_clauses[t.index()] == Some(clause))
+                        .all(|&t| t > idx)
+            });
+
+        if precedes_clause {
+            fields[idx.index()] = Some(TopologicalField::KOORD);
+        }
+    }
+
+    for (clause, &(head, finite)) in clauses.iter().enumerate() {
+        let own: Vec<_> = tokens
+            .iter()
+            .cloned()
+            .filter(|idx| token_clauses[idx.index()] == Some(clause))
+            .collect();
+        let chain = verb_chain(graph, scheme, head);
+
+        let verb_last = own.iter().any(|&idx| {
+            idx < finite && has_tag(graph, scheme, idx, |tag| {
+                scheme.is_complementizer_tag(tag) || scheme.is_relative_tag(tag) ||
+                    (parents[clause].is_some() && scheme.is_interrogative_tag(tag))
+            })
+        });
+
+        // Coordinating conjunctions and punctuation at the start of the clause.
+        let mut start = 0;
+        while start < own.len() {
+            let idx = own[start];
+            if is_punctuation(graph, scheme, idx) {
+                start += 1;
+            } else if has_tag(graph, scheme, idx, |tag| scheme.is_coordination_tag(tag)) {
+                fields[idx.index()] = Some(TopologicalField::KOORD);
+                start += 1;
+            } else {
+                break;
+            }
+        }
+        let own = &own[start..];
+
+        // The end of the left bracket.
+        let bracket = if verb_last {
+            complementizer_end(graph, scheme, own, finite)
+        } else {
+            fields[finite.index()] = Some(TopologicalField::LK);
+            Some(finite)
+        };
+
+        // The verb complex follows the left bracket.
+        let vc: Vec<_> = chain
+            .iter()
+            .cloned()
+            .filter(|&idx| idx != finite || verb_last)
+            .filter(|&idx| match bracket {
+                Some(bracket) => idx > bracket,
+                None => true,
+            })
+            .collect();
+        let vc_span = match (vc.first(), vc.last()) {
+            (Some(&first), Some(&last)) => Some((first, last)),
+            _ => None,
+        };
+
+        for &idx in own {
+            if fields[idx.index()].is_some() || is_punctuation(graph, scheme, idx) {
+                continue;
+            }
+
+            let field = match bracket {
+                Some(bracket) if idx <= bracket => {
+                    if verb_last {
+                        TopologicalField::C
+                    } else {
+                        TopologicalField::VF
+                    }
+                }
+                _ => match vc_span {
+                    Some((first, _)) if idx < first => TopologicalField::MF,
+                    Some((_, last)) if idx > last => TopologicalField::NF,
+                    Some(_) => TopologicalField::VC,
+                    None => TopologicalField::MF,
+                },
+            };
+
+            fields[idx.index()] = Some(field);
+        }
+    }
+
+    fields
+}
+
+/// Get the head of the verb chain of a finite verb.
+///
+/// In analyses where auxiliaries are dependents of the verb that they
+/// govern, this is the content verb. Otherwise, it is the finite verb.
+fn chain_head(graph: &DependencyGraph, scheme: &AnnotationScheme, idx: NodeIndex) -> NodeIndex {
+    let mut head = idx;
+    for _ in 0..graph.node_count() {
+        match chain_verb(graph, scheme, head, EdgeDirection::Incoming) {
+            Some(idx) => head = idx,
+            None => break,
+        }
+    }
+
+    head
+}
+
+/// Get the verbs and verb particles of the verb chain with the given head,
+/// in sentence order.
+fn verb_chain(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    head: NodeIndex,
+) -> Vec<NodeIndex> {
+    let mut chain = Vec::new();
+    let mut seen = HashSet::new();
+    let mut agenda = vec![head];
+    while let Some(idx) = agenda.pop() {
+        if !seen.insert(idx) {
+            continue;
+        }
+
+        if has_tag(graph, scheme, idx, |tag| scheme.is_verb_tag(tag)) {
+            chain.push(idx);
+        }
+
+        for edge in graph.edges_directed(idx, EdgeDirection::Outgoing) {
+            let target = edge.target();
+            if is_verb_chain_edge(graph, scheme, edge) {
+                agenda.push(target);
+            } else if is_relation_edge(edge.weight()) &&
+                has_tag(graph, scheme, target, |tag| scheme.is_verb_particle_tag(tag))
+            {
+                chain.push(target);
+            }
+        }
+    }
+
+    chain.sort();
+    chain.dedup();
+
+    chain
+}
+
+/// Find the last token of the C field of a verb-last clause: the
+/// complementizer, or the relative or interrogative phrase.
+fn complementizer_end(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    own: &[NodeIndex],
+    finite: NodeIndex,
+) -> Option<NodeIndex> {
+    let idx = own.iter().cloned().filter(|&idx| idx < finite).find(|&idx| {
+        has_tag(graph, scheme, idx, |tag| {
+            scheme.is_complementizer_tag(tag) || scheme.is_relative_tag(tag) ||
+                scheme.is_interrogative_tag(tag)
+        })
+    })?;
+
+    // Relative and interrogative determiners are part of a phrase that
+    // extends to their head, e.g. 'dessen Buch'.
+    match ancestor_tokens(graph, idx).next() {
+        Some(head) if head > idx && head < finite && own.contains(&head) &&
+            !has_tag(graph, scheme, head, |tag| scheme.is_verb_tag(tag)) => Some(head),
+        _ => Some(idx),
+    }
+}
+
+fn is_relation_edge(edge: &DependencyEdge) -> bool {
+    matches!(*edge, DependencyEdge::Relation(_))
+}
+
+fn is_punctuation(graph: &DependencyGraph, scheme: &AnnotationScheme, idx: NodeIndex) -> bool {
+    has_tag(graph, scheme, idx, |tag| scheme.is_punctuation_tag(tag))
+}
+
+fn has_tag<F>(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    idx: NodeIndex,
+    predicate: F,
+) -> bool
+where
+    F: Fn(&str) -> bool,
+{
+    match scheme.tag(graph[idx].token) {
+        Some(tag) => predicate(tag),
+        None => false,
+    }
+}
+
+/// Agreement of inferred topological fields with gold fields.
+#[derive(Clone, Debug, Default)]
+pub struct FieldAgreement {
+    confusion: BTreeMap<(Option<TopologicalField>, Option<TopologicalField>), usize>,
+}
+
+impl FieldAgreement {
+    pub fn new() -> Self {
+        FieldAgreement::default()
+    }
+
+    /// Add the gold and inferred field of a token.
+    pub fn add(&mut self, gold: Option<TopologicalField>, inferred: Option<TopologicalField>) {
+        *self.confusion.entry((gold, inferred)).or_insert(0) += 1;
+    }
+
+    /// Counts of (gold, inferred) field pairs.
+    pub fn confusion(&self) -> &BTreeMap<(Option<TopologicalField>, Option<TopologicalField>), usize> {
+        &self.confusion
+    }
+
+    /// The number of tokens.
+    pub fn tokens(&self) -> usize {
+        self.confusion.values().sum()
+    }
+
+    /// The fraction of tokens where the inferred field is the gold field.
+    pub fn accuracy(&self) -> f64 {
+        let correct: usize = self
+            .confusion
+            .iter()
+            .filter(|&(&(gold, inferred), _)| gold == inferred)
+            .map(|(_, &count)| count)
+            .sum();
+
+        correct as f64 / self.tokens() as f64
+    }
+
+    /// The number of tokens with the given gold field.
+    pub fn gold(&self, field: TopologicalField) -> usize {
+        self.count(|gold, _| gold == Some(field))
+    }
+
+    /// The number of tokens with the given inferred field.
+    pub fn inferred(&self, field: TopologicalField) -> usize {
+        self.count(|_, inferred| inferred == Some(field))
+    }
+
+    /// The number of tokens where the given field is the gold and
+    /// inferred field.
+    pub fn correct(&self, field: TopologicalField) -> usize {
+        self.count(|gold, inferred| gold == Some(field) && inferred == Some(field))
+    }
+
+    fn count<F>(&self, predicate: F) -> usize
+    where
+        F: Fn(Option<TopologicalField>, Option<TopologicalField>) -> bool,
+    {
+        self.confusion
+            .iter()
+            .filter(|&(&(gold, inferred), _)| predicate(gold, inferred))
+            .map(|(_, &count)| count)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::infer_fields;
+    use graph::sentence_to_graph;
+    use scheme::AnnotationScheme;
+    use test_util::sentence;
+
+    fn inferred(data: &str, scheme: &AnnotationScheme) -> Vec<&'static str> {
+        let sentence = sentence(data);
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        infer_fields(&graph, scheme)
+            .into_iter()
+            .map(|field| field.map(|field| field.name()).unwrap_or("_"))
+            .collect()
+    }
+
+    #[test]
+    fn verb_first() {
+        let fields = inferred(
+            "1  Hat       haben  VAFIN  VAFIN  _  0  ROOT     _  _
+             2  er        er     PPER   PPER   _  1  SUBJ     _  _
+             3  das       der    ART    ART    _  4  DET      _  _
+             4  Buch      Buch   NN     NN     _  6  OBJA     _  _
+             5  schon     schon  ADV    ADV    _  6  ADV      _  _
+             6  gelesen   lesen  VVPP   VVPP   _  1  AUX      _  _
+             7  ?         ?      $.     $.     _  1  -PUNCT-  _  _",
+            &AnnotationScheme::default(),
+        );
+        assert_eq!(fields, ["LK", "MF", "MF", "MF", "MF", "VC", "_"]);
+    }
+
+    #[test]
+    fn verb_second() {
+        let fields = inferred(
+            "1  Morgen  morgen    ADV    ADV    _  2  ADV      _  _
+             2  fängt   anfangen  VVFIN  VVFIN  _  0  ROOT     _  _
+             3  er      er        PPER   PPER   _  2  SUBJ     _  _
+             4  an      an        PTKVZ  PTKVZ  _  2  AVZ      _  _
+             5  ,       ,         $,     $,     _  2  -PUNCT-  _  _
+             6  sagt    sagen     VVFIN  VVFIN  _  2  S        _  _
+             7  sie     sie       PPER   PPER   _  6  SUBJ     _  _",
+            &AnnotationScheme::default(),
+        );
+        assert_eq!(fields, ["VF", "LK", "MF", "VC", "_", "LK", "MF"]);
+    }
+
+    #[test]
+    fn verb_last() {
+        let fields = inferred(
+            "1  Ich     ich      PPER   PPER   _  2  SUBJ     _  _
+             2  glaube  glauben  VVFIN  VVFIN  _  0  ROOT     _  _
+             3  ,       ,        $,     $,     _  2  -PUNCT-  _  _
+             4  dass    dass     KOUS   KOUS   _  7  KONJ     _  _
+             5  er      er       PPER   PPER   _  7  SUBJ     _  _
+             6  kommen  kommen   VVINF  VVINF  _  7  AUX      _  _
+             7  wird    werden   VAFIN  VAFIN  _  2  OBJC     _  _",
+            &AnnotationScheme::default(),
+        );
+        assert_eq!(fields, ["VF", "LK", "_", "C", "MF", "VC", "VC"]);
+    }
+
+    #[test]
+    fn relative_clause() {
+        let fields = inferred(
+            "1   Das    der    ART    ART    _  2  DET      _  _
+             2   Buch   Buch   NN     NN     _  8  SUBJ     _  _
+             3   ,      ,      $,     $,     _  2  -PUNCT-  _  _
+             4   dessen der    PRELAT PRELAT _  5  DET      _  _
+             5   Autor  Autor  NN     NN     _  7  SUBJ     _  _
+             6   hier   hier   ADV    ADV    _  7  ADV      _  _
+             7   lebt   leben  VVFIN  VVFIN  _  2  REL      _  _
+             8   ist    sein   VAFIN  VAFIN  _  0  ROOT     _  _
+             9   gut    gut    ADJD   ADJD   _  8  PRED     _  _
+             10  .      .      $.     $.     _  8  -PUNCT-  _  _",
+            &AnnotationScheme::default(),
+        );
+
+        // The relative phrase extends to the head of the relative
+        // determiner.
+        assert_eq!(
+            fields,
+            ["VF", "VF", "_", "C", "C", "MF", "VC", "LK", "MF", "_"]
+        );
+    }
+
+    #[test]
+    fn coordination() {
+        // Clause-initial conjunction.
+        let fields = inferred(
+            "1  Und   und     KON    KON    _  2  KON   _  _
+             2  kommt kommen  VVFIN  VVFIN  _  0  ROOT  _  _
+             3  er    er      PPER   PPER   _  2  SUBJ  _  _",
+            &AnnotationScheme::default(),
+        );
+        assert_eq!(fields, ["KOORD", "LK", "MF"]);
+
+        // Conjunction attached to the first conjunct.
+        let fields = inferred(
+            "1  Er     er      PPER   PPER   _  2  SUBJ     _  _
+             2  kommt  kommen  VVFIN  VVFIN  _  0  ROOT     _  _
+             3  und    und     KON    KON    _  2  KON      _  _
+             4  sie    sie     PPER   PPER   _  5  SUBJ     _  _
+             5  geht   gehen   VVFIN  VVFIN  _  3  CJ       _  _
+             6  .      .       $.     $.     _  2  -PUNCT-  _  _",
+            &AnnotationScheme::default(),
+        );
+        assert_eq!(fields, ["VF", "LK", "KOORD", "VF", "LK", "_"]);
+    }
+
+    #[test]
+    fn scheme_tags() {
+        let scheme = AnnotationScheme::from_toml(
+            "[tags]\n\
+             complementizers = [\"SCONJ\"]\n\
+             punctuation_prefixes = [\"PUNCT\"]",
+        ).unwrap();
+        let fields = inferred(
+            "1  Ich     ich      PPER   PPER   _  2  SUBJ     _  _
+             2  glaube  glauben  VVFIN  VVFIN  _  0  ROOT     _  _
+             3  ,       ,        PUNCT  PUNCT  _  2  -PUNCT-  _  _
+             4  dass    dass     SCONJ  SCONJ  _  6  KONJ     _  _
+             5  er      er       PPER   PPER   _  6  SUBJ     _  _
+             6  kommt   kommen   VVFIN  VVFIN  _  2  OBJC     _  _",
+            &scheme,
+        );
+        assert_eq!(fields, ["VF", "LK", "_", "C", "MF", "VC"]);
+
+        // Without finite verb tags, no clauses are found.
+        let fields = inferred(
+            "1  Er     er      PRON  PRON  _  2  nsubj  _  _
+             2  kommt  kommen  VERB  VERB  _  0  root   _  _",
+            &AnnotationScheme::preset("ud").unwrap(),
+        );
+        assert_eq!(fields, ["_", "_"]);
+    }
+}
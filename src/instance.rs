@@ -0,0 +1,287 @@
+use std::io::Write;
+
+use error::{Error, Result};
+
+/// Columns of an ambiguous PP instance line.
+///
+/// Instance lines are written by `extract-ambiguous-pps`:
+///
+/// ```text
+/// [SOURCE] SENT_ID [P_ID] P P_POS P_TF OBJ OBJ_POS OBJ_TF (FORM POS TF DISTANCE RANK HEAD [SCORE])...
+/// ```
+///
+/// `extract-ambiguous-attachments` writes the same format for other
+/// dependents, the preposition columns then hold the dependent.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InstanceFormat {
+    /// The line starts with the input file name.
+    pub source: bool,
+
+    /// The sentence identifier is followed by the position of the
+    /// preposition.
+    pub position: bool,
+
+    /// Every candidate has a score column.
+    pub scores: bool,
+}
+
+impl InstanceFormat {
+    fn candidate_columns(&self) -> usize {
+        if self.scores {
+            7
+        } else {
+            6
+        }
+    }
+}
+
+/// A token of an ambiguous PP instance.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InstanceToken {
+    pub form: String,
+    pub pos: String,
+    pub field: String,
+}
+
+/// A candidate head of an ambiguous PP instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstanceCandidate {
+    pub token: InstanceToken,
+
+    /// The offset of the candidate relative to the preposition.
+    pub distance: isize,
+
+    /// The rank of the candidate among the candidates on the same side
+    /// of the preposition, negative for candidates before the preposition.
+    pub rank: isize,
+
+    /// The candidate is the head of the PP.
+    pub head: bool,
+
+    /// The score of a model, if any.
+    pub score: Option<f64>,
+}
+
+/// An ambiguous PP with its candidate heads.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PpInstance {
+    pub source: Option<String>,
+    pub sent_id: String,
+
+    /// The 1-based position of the preposition in the sentence.
+    pub position: Option<usize>,
+
+    pub preposition: InstanceToken,
+    pub object: InstanceToken,
+    pub candidates: Vec<InstanceCandidate>,
+}
+
+impl PpInstance {
+    /// Parse an instance line.
+    pub fn parse(line: &str, format: InstanceFormat) -> Result<PpInstance> {
+        let mut columns: Vec<_> = line.split_whitespace().collect();
+
+        let source = if format.source {
+            if columns.is_empty() {
+                return Err(Error::Instance("missing file name".to_owned()));
+            }
+
+            Some(columns.remove(0).to_owned())
+        } else {
+            None
+        };
+
+        if columns.is_empty() {
+            return Err(Error::Instance("missing sentence identifier".to_owned()));
+        }
+        let sent_id = columns.remove(0).to_owned();
+
+        let position = if format.position {
+            if columns.is_empty() {
+                return Err(Error::Instance("missing preposition position".to_owned()));
+            }
+
+            Some(parse_column(columns.remove(0), "preposition position")?)
+        } else {
+            None
+        };
+
+        let candidate_columns = format.candidate_columns();
+        if columns.len() < 6 || (columns.len() - 6) % candidate_columns != 0 {
+            return Err(Error::Instance(format!(
+                "expected 6 + {}n columns after the sentence identifier, got {}",
+                candidate_columns,
+                columns.len()
+            )));
+        }
+
+        let candidates = columns[6..]
+            .chunks(candidate_columns)
+            .map(|candidate| parse_candidate(candidate, format))
+            .collect::<Result<_>>()?;
+
+        Ok(PpInstance {
+            source,
+            sent_id,
+            position,
+            preposition: parse_token(&columns[0..3]),
+            object: parse_token(&columns[3..6]),
+            candidates,
+        })
+    }
+
+    /// The index of the candidate that is the head of the PP.
+    pub fn head(&self) -> Option<usize> {
+        self.candidates.iter().position(|candidate| candidate.head)
+    }
+
+    /// Write the instance in the format that is read by `parse`.
+    ///
+    /// Candidate scores are written when all candidates have a score.
+    pub fn write(&self, writer: &mut dyn Write) -> Result<()> {
+        if let Some(ref source) = self.source {
+            write!(writer, "{} ", source)?;
+        }
+
+        write!(writer, "{}", self.sent_id)?;
+
+        if let Some(position) = self.position {
+            write!(writer, " {}", position)?;
+        }
+
+        write!(
+            writer,
+            " {} {}",
+            token_columns(&self.preposition),
+            token_columns(&self.object)
+        )?;
+
+        let scores = self.candidates.iter().all(|c| c.score.is_some());
+
+        for candidate in &self.candidates {
+            write!(
+                writer,
+                " {} {} {} {}",
+                token_columns(&candidate.token),
+                candidate.distance,
+                candidate.rank,
+                if candidate.head { 1 } else { 0 }
+            )?;
+
+            if let (true, Some(score)) = (scores, candidate.score) {
+                write!(writer, " {}", score)?;
+            }
+        }
+
+        writeln!(writer)?;
+
+        Ok(())
+    }
+}
+
+fn parse_token(columns: &[&str]) -> InstanceToken {
+    InstanceToken {
+        form: columns[0].to_owned(),
+        pos: columns[1].to_owned(),
+        field: columns[2].to_owned(),
+    }
+}
+
+fn token_columns(token: &InstanceToken) -> String {
+    format!("{} {} {}", token.form, token.pos, token.field)
+}
+
+fn parse_candidate(columns: &[&str], format: InstanceFormat) -> Result<InstanceCandidate> {
+    let score = if format.scores {
+        Some(parse_column(columns[6], "score")?)
+    } else {
+        None
+    };
+
+    let head = match columns[5] {
+        "0" => false,
+        "1" => true,
+        head => return Err(Error::Instance(format!("invalid head flag: {}", head))),
+    };
+
+    Ok(InstanceCandidate {
+        token: parse_token(&columns[0..3]),
+        distance: parse_column(columns[3], "distance")?,
+        rank: parse_column(columns[4], "rank")?,
+        head,
+        score,
+    })
+}
+
+fn parse_column<T>(column: &str, name: &str) -> Result<T>
+where
+    T: ::std::str::FromStr,
+{
+    column
+        .parse()
+        .map_err(|_| Error::Instance(format!("invalid {}: {}", name, column)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InstanceFormat, PpInstance};
+
+    #[test]
+    fn parse_write_roundtrip() {
+        let format = InstanceFormat {
+            source: true,
+            position: true,
+            scores: true,
+        };
+        let line = "train.conll s12 4 mit APPR MF Messer NN MF \
+                    schneidet VVFIN LK -2 -1 1 0.75 Brot NN MF -1 -1 0 0.25\n";
+
+        let instance = PpInstance::parse(line, format).unwrap();
+        assert_eq!(instance.source.as_deref(), Some("train.conll"));
+        assert_eq!(instance.sent_id, "s12");
+        assert_eq!(instance.position, Some(4));
+        assert_eq!(instance.preposition.form, "mit");
+        assert_eq!(instance.object.pos, "NN");
+        assert_eq!(instance.candidates.len(), 2);
+        assert_eq!(instance.candidates[0].distance, -2);
+        assert_eq!(instance.candidates[1].rank, -1);
+        assert_eq!(instance.candidates[1].score, Some(0.25));
+        assert_eq!(instance.head(), Some(0));
+
+        let mut written = Vec::new();
+        instance.write(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), line);
+    }
+
+    #[test]
+    fn scores_are_only_written_for_all_candidates() {
+        let format = InstanceFormat::default();
+        let line = "1 mit APPR MF Messer NN MF schneidet VVFIN LK -2 -1 1 Brot NN MF -1 -1 0";
+
+        let mut instance = PpInstance::parse(line, format).unwrap();
+        assert_eq!(instance.source, None);
+        assert_eq!(instance.position, None);
+        assert_eq!(instance.candidates[0].score, None);
+
+        instance.candidates[0].score = Some(0.5);
+
+        let mut written = Vec::new();
+        instance.write(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), format!("{}\n", line));
+    }
+
+    #[test]
+    fn malformed_instances_are_errors() {
+        let format = InstanceFormat::default();
+
+        for line in &[
+            "",
+            "1 mit APPR MF Messer NN",
+            "1 mit APPR MF Messer NN MF schneidet VVFIN LK -2 -1",
+            "1 mit APPR MF Messer NN MF schneidet VVFIN LK -2 -1 2",
+            "1 mit APPR MF Messer NN MF schneidet VVFIN LK x -1 1",
+        ] {
+            assert!(PpInstance::parse(line, format).is_err(), "{}", line);
+        }
+    }
+}
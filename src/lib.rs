@@ -1,13 +1,96 @@
 extern crate conllx;
+extern crate flate2;
+extern crate glob;
+#[macro_use]
+extern crate lazy_static;
 extern crate petgraph;
+extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tempfile;
+extern crate toml;
+extern crate xz2;
+extern crate zstd;
 
 #[macro_use]
 mod macros;
 
+mod assoc;
+pub use assoc::{Association, AssociationMeasure, AssociationTable, ContingencyCounts};
+
+mod attachment;
+pub use attachment::{attachment_ambiguities, AttachmentAmbiguity, AttachmentRules, Scope,
+                     SearchDirection, ATTACHMENT_PRESETS};
+
+mod backoff;
+pub use backoff::{AttachmentCounts, AttachmentTuple, BackoffModel};
+
+mod clause;
+pub use clause::{segment_clauses, Clause, ClauseType, Clauses};
+
 mod cmd;
-pub use cmd::{or_exit, or_stdin, or_stdout};
+pub use cmd::{Compression, Input, Output, OutputWriter, INPUTS_USAGE, inputs_or_stdin, or_exit,
+              or_stdin, or_stdout, or_warn};
+
+mod conllu;
+pub use conllu::{ConlluAnnotations, ConlluReader, ConlluSentence, EmptyNode, EnhancedDependency,
+                 MultiwordToken, TokenId};
+
+mod count;
+pub use count::CooccurrenceCounter;
+
+mod contexts;
+pub use contexts::{dependency_contexts, DependencyContext};
+
+mod corpus;
+pub use corpus::{CorpusReader, CorpusSentence, InputFormat};
+
+mod error;
+pub use error::{Error, Result};
+
+mod frame;
+pub use frame::{resolve_verb, subcat_frames, FrameArgument, SubcatFrame};
 
 mod graph;
 pub use graph::{AncestorTokens, AdjacentTokens, DependencyGraph, DependencyEdge, DependencyNode,
-                Direction, adjacent_tokens, ancestor_tokens, first_matching_edge,
-                sentence_to_graph};
+                Direction, ROOT_FORM, adjacent_tokens, ancestor_tokens, first_matching_edge,
+                root_tokens, sentence_to_graph, virtual_root};
+
+mod infer;
+pub use infer::{infer_fields, FieldAgreement};
+
+mod instance;
+pub use instance::{InstanceCandidate, InstanceFormat, InstanceToken, PpInstance};
+
+mod matrix;
+pub use matrix::{CooccurrenceMatrix, CsrMatrix};
+
+mod owned;
+pub use owned::{Label, OwnedDependencyEdge, OwnedDependencyGraph, OwnedDependencyNode};
+
+mod pipeline;
+pub use pipeline::Pipeline;
+
+mod pp;
+pub use pp::{preposition_complement, prepositional_phrases, PrepositionalPhrase};
+
+mod query;
+pub use query::{Match, Pattern, PatternError};
+
+mod ranking;
+pub use ranking::{closest_ranking, right_association_ranking, score_ranking, verb_ranking,
+                  RankPrior, RankingMetrics};
+
+mod scheme;
+pub use scheme::{AnnotationScheme, PpAnalysis, Relations, Tags, TopologicalFields, PRESETS};
+
+#[cfg(test)]
+mod test_util;
+
+mod topo;
+pub use topo::{feature_value, field_spans, set_feature_value, FieldSpan, FieldSpans,
+               TopologicalField};
+
+mod writer;
+pub use writer::ConllxWriter;
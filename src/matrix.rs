@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+/// Co-occurrence counts of row and column items.
+///
+/// Use `to_csr` to get the counts as a sparse matrix with vocabularies.
+#[derive(Default)]
+pub struct CooccurrenceMatrix {
+    rows: Vocabulary,
+    cols: Vocabulary,
+    counts: HashMap<(usize, usize), u64>,
+}
+
+impl CooccurrenceMatrix {
+    pub fn new() -> Self {
+        CooccurrenceMatrix::default()
+    }
+
+    /// Add `count` co-occurrences of a row and a column item.
+    pub fn add(&mut self, row: &str, col: &str, count: u64) {
+        let row = self.rows.index(row);
+        let col = self.cols.index(col);
+        *self.counts.entry((row, col)).or_insert(0) += count;
+    }
+
+    /// Convert the counts to a compressed sparse row matrix.
+    ///
+    /// Counts lower than `min_freq` are removed. Rows and columns are
+    /// sorted by decreasing frequency, ties are sorted by item. Items
+    /// that have no remaining counts are not in the vocabularies.
+    pub fn to_csr(&self, min_freq: u64) -> CsrMatrix {
+        let mut row_freqs = vec![0; self.rows.items.len()];
+        let mut col_freqs = vec![0; self.cols.items.len()];
+        for (&(row, col), &count) in &self.counts {
+            if count >= min_freq {
+                row_freqs[row] += count;
+                col_freqs[col] += count;
+            }
+        }
+
+        let (rows, row_map) = self.rows.sorted(&row_freqs);
+        let (cols, col_map) = self.cols.sorted(&col_freqs);
+
+        let mut entries: Vec<_> = self
+            .counts
+            .iter()
+            .filter(|&(_, &count)| count >= min_freq)
+            .map(|(&(row, col), &count)| (row_map[row], col_map[col], count))
+            .collect();
+        entries.sort_unstable();
+
+        let mut indptr = vec![0; rows.len() + 1];
+        for &(row, _, _) in &entries {
+            indptr[row + 1] += 1;
+        }
+        for row in 0..rows.len() {
+            indptr[row + 1] += indptr[row];
+        }
+
+        CsrMatrix {
+            rows,
+            cols,
+            indptr,
+            indices: entries.iter().map(|&(_, col, _)| col).collect(),
+            data: entries.iter().map(|&(_, _, count)| count).collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Vocabulary {
+    indices: HashMap<String, usize>,
+    items: Vec<String>,
+}
+
+impl Vocabulary {
+    fn index(&mut self, item: &str) -> usize {
+        if let Some(&idx) = self.indices.get(item) {
+            return idx;
+        }
+
+        let idx = self.items.len();
+        self.indices.insert(item.to_owned(), idx);
+        self.items.push(item.to_owned());
+        idx
+    }
+
+    /// Sort the items with a non-zero frequency by decreasing frequency.
+    /// Returns the sorted items and a mapping from old to new indices.
+    fn sorted(&self, freqs: &[u64]) -> (Vec<String>, Vec<usize>) {
+        let mut order: Vec<_> = (0..self.items.len()).filter(|&idx| freqs[idx] > 0).collect();
+        order.sort_by(|&a, &b| match freqs[b].cmp(&freqs[a]) {
+            Ordering::Equal => self.items[a].cmp(&self.items[b]),
+            ordering => ordering,
+        });
+
+        let mut mapping = vec![0; self.items.len()];
+        for (new_idx, &old_idx) in order.iter().enumerate() {
+            mapping[old_idx] = new_idx;
+        }
+
+        (
+            order.into_iter().map(|idx| self.items[idx].clone()).collect(),
+            mapping,
+        )
+    }
+}
+
+/// A sparse matrix in compressed sparse row (CSR) format.
+#[derive(Clone, Debug)]
+pub struct CsrMatrix {
+    /// The row vocabulary.
+    pub rows: Vec<String>,
+
+    /// The column vocabulary.
+    pub cols: Vec<String>,
+
+    /// The entries of row `i` are stored in `indptr[i]..indptr[i + 1]`.
+    pub indptr: Vec<usize>,
+
+    /// The column of each entry.
+    pub indices: Vec<usize>,
+
+    /// The value of each entry.
+    pub data: Vec<u64>,
+}
+
+impl CsrMatrix {
+    /// Write the matrix in Matrix Market coordinate format.
+    pub fn write_matrix_market(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "%%MatrixMarket matrix coordinate integer general")?;
+        writeln!(
+            writer,
+            "{} {} {}",
+            self.rows.len(),
+            self.cols.len(),
+            self.data.len()
+        )?;
+
+        for row in 0..self.rows.len() {
+            for idx in self.indptr[row]..self.indptr[row + 1] {
+                writeln!(writer, "{} {} {}", row + 1, self.indices[idx] + 1, self.data[idx])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the `indptr` array in NumPy `.npy` format.
+    pub fn write_indptr_npy(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_npy(writer, self.indptr.iter().map(|&v| v as i64))
+    }
+
+    /// Write the `indices` array in NumPy `.npy` format.
+    pub fn write_indices_npy(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_npy(writer, self.indices.iter().map(|&v| v as i64))
+    }
+
+    /// Write the `data` array in NumPy `.npy` format.
+    pub fn write_data_npy(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_npy(writer, self.data.iter().map(|&v| v as i64))
+    }
+}
+
+/// Write a one-dimensional array of 64-bit integers in `.npy` format
+/// (version 1.0).
+fn write_npy<I>(writer: &mut dyn Write, values: I) -> io::Result<()>
+where
+    I: ExactSizeIterator<Item = i64>,
+{
+    let mut header = format!(
+        "{{'descr': '<i8', 'fortran_order': False, 'shape': ({},), }}",
+        values.len()
+    );
+
+    // The magic string, version, header length, and header are padded
+    // to a multiple of 64 bytes, the header ends with a newline.
+    let unpadded_len = 10 + header.len() + 1;
+    let padding = (64 - unpadded_len % 64) % 64;
+    header.extend((0..padding).map(|_| ' '));
+    header.push('\n');
+
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CooccurrenceMatrix;
+
+    fn matrix() -> CooccurrenceMatrix {
+        let mut matrix = CooccurrenceMatrix::new();
+        matrix.add("a", "x", 2);
+        matrix.add("a", "y", 1);
+        matrix.add("b", "x", 5);
+        matrix.add("c", "z", 1);
+        matrix.add("a", "x", 1);
+        matrix
+    }
+
+    #[test]
+    fn csr_is_sorted_by_frequency() {
+        let csr = matrix().to_csr(1);
+
+        assert_eq!(csr.rows, vec!["b", "a", "c"]);
+        assert_eq!(csr.cols, vec!["x", "y", "z"]);
+        assert_eq!(csr.indptr, vec![0, 1, 3, 4]);
+        assert_eq!(csr.indices, vec![0, 0, 1, 2]);
+        assert_eq!(csr.data, vec![5, 3, 1, 1]);
+
+        let mut mtx = Vec::new();
+        csr.write_matrix_market(&mut mtx).unwrap();
+        assert_eq!(
+            String::from_utf8(mtx).unwrap(),
+            "%%MatrixMarket matrix coordinate integer general\n\
+             3 3 4\n\
+             1 1 5\n\
+             2 1 3\n\
+             2 2 1\n\
+             3 3 1\n"
+        );
+    }
+
+    #[test]
+    fn csr_min_freq_removes_items() {
+        let csr = matrix().to_csr(2);
+
+        assert_eq!(csr.rows, vec!["b", "a"]);
+        assert_eq!(csr.cols, vec!["x"]);
+        assert_eq!(csr.indptr, vec![0, 1, 2]);
+        assert_eq!(csr.indices, vec![0, 0]);
+        assert_eq!(csr.data, vec![5, 3]);
+    }
+
+    #[test]
+    fn npy_header_is_padded() {
+        let csr = matrix().to_csr(1);
+        let mut npy = Vec::new();
+        csr.write_data_npy(&mut npy).unwrap();
+
+        let header = "{'descr': '<i8', 'fortran_order': False, 'shape': (4,), }";
+        let header_len = 128 - 10;
+
+        let mut expected = b"\x93NUMPY\x01\x00".to_vec();
+        expected.extend_from_slice(&[header_len as u8, 0]);
+        expected.extend_from_slice(header.as_bytes());
+        expected.extend(vec![b' '; header_len - header.len() - 1]);
+        expected.push(b'\n');
+        assert_eq!(expected.len(), 128);
+
+        for &value in &[5i64, 3, 1, 1] {
+            expected.extend_from_slice(&value.to_le_bytes());
+        }
+
+        assert_eq!(npy, expected);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use conllx::{Sentence, Token};
+use petgraph::{Directed, Graph};
+
+use error::Result;
+use graph::{sentence_to_graph, DependencyEdge, DependencyGraph, DependencyNode, ROOT_TOKEN};
+
+/// An interned relation label.
+///
+/// Labels are interned per graph, use `OwnedDependencyGraph::label` to
+/// get the string form.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Label(usize);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OwnedDependencyEdge {
+    Relation(Option<Label>),
+    Root(Option<Label>),
+    Precedence,
+}
+
+#[derive(Clone, Debug)]
+pub struct OwnedDependencyNode {
+    pub token: Token,
+    pub offset: usize,
+    root: bool,
+}
+
+impl OwnedDependencyNode {
+    /// Returns `true` if this is the virtual root node.
+    pub fn is_root(&self) -> bool {
+        self.root
+    }
+}
+
+/// A dependency graph that owns its tokens and labels.
+///
+/// In contrast to `DependencyGraph`, this graph does not borrow from a
+/// `Sentence`, so it can be stored or sent to another thread. Node and
+/// edge indices are the same as those of the graph that it was converted
+/// from. Use `as_graph` to get a `DependencyGraph` for the functions that
+/// operate on borrowed graphs.
+#[derive(Clone, Debug)]
+pub struct OwnedDependencyGraph {
+    graph: Graph<OwnedDependencyNode, OwnedDependencyEdge, Directed>,
+    labels: Vec<String>,
+}
+
+impl OwnedDependencyGraph {
+    /// Convert a sentence to an owned dependency graph.
+    ///
+    /// See `sentence_to_graph` for the meaning of `projective` and `root`.
+    pub fn from_sentence(sentence: &Sentence, projective: bool, root: bool) -> Result<Self> {
+        Ok(Self::from_graph(&sentence_to_graph(
+            sentence, projective, root,
+        )?))
+    }
+
+    /// Construct an owned copy of a borrowed dependency graph.
+    pub fn from_graph(graph: &DependencyGraph) -> Self {
+        let mut interner = Interner::default();
+
+        let graph = graph.map(
+            |_, node| OwnedDependencyNode {
+                token: node.token.clone(),
+                offset: node.offset,
+                root: node.is_root(),
+            },
+            |_, edge| match *edge {
+                DependencyEdge::Relation(label) => {
+                    OwnedDependencyEdge::Relation(label.map(|l| interner.intern(l)))
+                }
+                DependencyEdge::Root(label) => {
+                    OwnedDependencyEdge::Root(label.map(|l| interner.intern(l)))
+                }
+                DependencyEdge::Precedence => OwnedDependencyEdge::Precedence,
+            },
+        );
+
+        OwnedDependencyGraph {
+            graph,
+            labels: interner.labels,
+        }
+    }
+
+    /// Get a borrowed view of the graph.
+    pub fn as_graph(&self) -> DependencyGraph<'_> {
+        self.graph.map(
+            |_, node| DependencyNode {
+                token: if node.root { &ROOT_TOKEN } else { &node.token },
+                offset: node.offset,
+            },
+            |_, edge| match *edge {
+                OwnedDependencyEdge::Relation(label) => {
+                    DependencyEdge::Relation(label.map(|l| self.label(l)))
+                }
+                OwnedDependencyEdge::Root(label) => {
+                    DependencyEdge::Root(label.map(|l| self.label(l)))
+                }
+                OwnedDependencyEdge::Precedence => DependencyEdge::Precedence,
+            },
+        )
+    }
+
+    /// Get the underlying graph.
+    pub fn graph(&self) -> &Graph<OwnedDependencyNode, OwnedDependencyEdge, Directed> {
+        &self.graph
+    }
+
+    /// Get the string form of a label.
+    pub fn label(&self, label: Label) -> &str {
+        &self.labels[label.0]
+    }
+
+    /// Look up the interned form of a label.
+    ///
+    /// Returns `None` if the label is not used in this graph.
+    pub fn lookup_label(&self, label: &str) -> Option<Label> {
+        self.labels.iter().position(|l| l == label).map(Label)
+    }
+
+    /// Convert the graph back to a sentence.
+    ///
+    /// The tokens are returned in sentence order, the virtual root is not
+    /// included.
+    pub fn to_sentence(&self) -> Sentence {
+        let mut nodes: Vec<_> = self
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|n| &n.weight)
+            .filter(|n| !n.root)
+            .collect();
+        nodes.sort_by_key(|n| n.offset);
+
+        Sentence::new(nodes.into_iter().map(|n| n.token.clone()).collect())
+    }
+}
+
+#[derive(Default)]
+struct Interner {
+    indices: HashMap<String, Label>,
+    labels: Vec<String>,
+}
+
+impl Interner {
+    fn intern(&mut self, label: &str) -> Label {
+        if let Some(&interned) = self.indices.get(label) {
+            return interned;
+        }
+
+        let interned = Label(self.labels.len());
+        self.labels.push(label.to_owned());
+        self.indices.insert(label.to_owned(), interned);
+        interned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::{OwnedDependencyEdge, OwnedDependencyGraph};
+    use graph::{sentence_to_graph, virtual_root, DependencyEdge};
+    use test_util::sentence;
+
+    #[test]
+    fn round_trip_preserves_graph() {
+        let sentence = sentence(
+            "1  Peter      Peter      NE     NE     _  2  SUBJ
+             2  schneidet  schneiden  VVFIN  VVFIN  _  0  ROOT
+             3  Brot       Brot       NN     NN     _  2  OBJA
+             4  mit        mit        APPR   APPR   _  2  PP
+             5  dem        der        ART    ART    _  6  DET
+             6  Messer     Messer     NN     NN     _  4  PN",
+        );
+        let graph = sentence_to_graph(&sentence, false, true).unwrap();
+
+        let owned = OwnedDependencyGraph::from_graph(&graph);
+        let borrowed = owned.as_graph();
+
+        assert_eq!(borrowed.node_count(), graph.node_count());
+        assert_eq!(borrowed.edge_count(), graph.edge_count());
+
+        for idx in graph.node_indices() {
+            assert_eq!(borrowed[idx].offset, graph[idx].offset);
+            assert_eq!(borrowed[idx].token, graph[idx].token);
+        }
+
+        for idx in graph.edge_indices() {
+            assert_eq!(borrowed.edge_endpoints(idx), graph.edge_endpoints(idx));
+            assert_eq!(borrowed[idx], graph[idx]);
+        }
+
+        let root = virtual_root(&borrowed).unwrap();
+        assert_eq!(root, NodeIndex::new(6));
+        assert!(owned.graph()[root].is_root());
+        assert!(borrowed[root].is_root());
+        assert_eq!(
+            borrowed
+                .find_edge(root, NodeIndex::new(1))
+                .map(|e| &borrowed[e]),
+            Some(&DependencyEdge::Root(Some("ROOT")))
+        );
+
+        assert_eq!(owned.to_sentence(), sentence);
+    }
+
+    #[test]
+    fn labels_are_interned() {
+        let sentence = sentence(
+            "1  der   der   ART  ART  _  2  DET
+             2  Mann  Mann  NN   NN   _  0  ROOT
+             3  und   und   KON  KON  _  2  KON
+             4  die   die   ART  ART  _  5  DET
+             5  Frau  Frau  NN   NN   _  3  CJ",
+        );
+        let owned = OwnedDependencyGraph::from_sentence(&sentence, false, false).unwrap();
+
+        let det = owned.lookup_label("DET").unwrap();
+        assert_eq!(owned.label(det), "DET");
+        assert_eq!(owned.lookup_label("OBJA"), None);
+
+        let graph = owned.graph();
+        let det_edges = graph
+            .raw_edges()
+            .iter()
+            .filter(|e| e.weight == OwnedDependencyEdge::Relation(Some(det)))
+            .count();
+        assert_eq!(det_edges, 2);
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use corpus::CorpusSentence;
+use error::Result;
+use graph::{sentence_to_graph, DependencyGraph};
+
+/// The number of queued sentences per worker thread.
+const QUEUE_SIZE_PER_THREAD: usize = 64;
+
+/// An extraction pipeline.
+///
+/// The pipeline converts the sentences of a corpus to dependency graphs
+/// and applies an extractor to each graph. Sentences are processed on a
+/// pool of worker threads, the results are passed to a sink in the order
+/// of the input sentences.
+#[derive(Clone, Copy, Debug)]
+pub struct Pipeline {
+    threads: usize,
+    projective: bool,
+    root: bool,
+}
+
+impl Pipeline {
+    /// Construct a pipeline with the given number of worker threads.
+    ///
+    /// With a single thread, sentences are processed on the calling
+    /// thread.
+    pub fn new(threads: usize) -> Self {
+        Pipeline {
+            threads: threads.max(1),
+            projective: false,
+            root: false,
+        }
+    }
+
+    /// Use projective heads (see `sentence_to_graph`).
+    pub fn projective(mut self, projective: bool) -> Self {
+        self.projective = projective;
+        self
+    }
+
+    /// Attach root tokens to a virtual root node (see `sentence_to_graph`).
+    pub fn root(mut self, root: bool) -> Self {
+        self.root = root;
+        self
+    }
+
+    /// Run the pipeline.
+    ///
+    /// `extractor` is called for each sentence and its graph, `sink` is
+    /// called with the result of each sentence in input order. Errors
+    /// from graph construction and the extractor are passed to the sink,
+    /// so that it can decide to skip the sentence. Errors from reading the
+    /// corpus and errors of the sink stop the pipeline and are returned.
+    pub fn run<I, E, T, S>(&self, sentences: I, extractor: E, sink: S) -> Result<()>
+    where
+        I: IntoIterator<Item = Result<CorpusSentence>>,
+        E: Fn(&CorpusSentence, &DependencyGraph) -> Result<T> + Sync,
+        T: Send,
+        S: FnMut(Result<T>) -> Result<()>,
+    {
+        if self.threads == 1 {
+            self.run_sequential(sentences, extractor, sink)
+        } else {
+            self.run_parallel(sentences, extractor, sink)
+        }
+    }
+
+    fn extract<E, T>(&self, sentence: &CorpusSentence, extractor: &E) -> Result<T>
+    where
+        E: Fn(&CorpusSentence, &DependencyGraph) -> Result<T>,
+    {
+        let graph = sentence_to_graph(&sentence.sentence, self.projective, self.root)
+            .map_err(|e| e.in_sentence(sentence.id))?;
+        extractor(sentence, &graph).map_err(|e| e.in_sentence(sentence.id))
+    }
+
+    fn run_sequential<I, E, T, S>(&self, sentences: I, extractor: E, mut sink: S) -> Result<()>
+    where
+        I: IntoIterator<Item = Result<CorpusSentence>>,
+        E: Fn(&CorpusSentence, &DependencyGraph) -> Result<T>,
+        S: FnMut(Result<T>) -> Result<()>,
+    {
+        for sentence in sentences {
+            let sentence = sentence?;
+            sink(self.extract(&sentence, &extractor))?;
+        }
+
+        Ok(())
+    }
+
+    fn run_parallel<I, E, T, S>(&self, sentences: I, extractor: E, mut sink: S) -> Result<()>
+    where
+        I: IntoIterator<Item = Result<CorpusSentence>>,
+        E: Fn(&CorpusSentence, &DependencyGraph) -> Result<T> + Sync,
+        T: Send,
+        S: FnMut(Result<T>) -> Result<()>,
+    {
+        let mut panic = None;
+
+        let result = thread::scope(|scope| {
+            // The senders and receivers are dropped when the pipeline stops,
+            // which ends the worker threads. The job receiver is owned by the
+            // workers, so that sending fails rather than blocks when all
+            // workers are gone.
+            let (job_tx, job_rx) = sync_channel(self.threads * QUEUE_SIZE_PER_THREAD);
+            let job_rx = Arc::new(Mutex::new(job_rx));
+            let (result_tx, result_rx) = channel();
+
+            for _ in 0..self.threads {
+                let job_rx = Arc::clone(&job_rx);
+                let result_tx = result_tx.clone();
+                let extractor = &extractor;
+
+                scope.spawn(move || loop {
+                    let job = match job_rx.lock() {
+                        Ok(job_rx) => job_rx.recv(),
+                        Err(_) => break,
+                    };
+                    let (seq, sentence): (usize, CorpusSentence) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+
+                    // A panic is passed to the main thread, so that the
+                    // pipeline stops rather than waiting for the result.
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        self.extract(&sentence, extractor)
+                    }));
+
+                    if result_tx.send((seq, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(job_rx);
+            drop(result_tx);
+
+            let mut results = Reorder::default();
+            let mut receive = |seq, result: thread::Result<Result<T>>| -> Result<bool> {
+                match result {
+                    Ok(result) => results.push(seq, result),
+                    Err(payload) => {
+                        panic = Some(payload);
+                        return Ok(false);
+                    }
+                }
+
+                while let Some(result) = results.pop() {
+                    sink(result)?;
+                }
+
+                Ok(true)
+            };
+
+            // Results are drained while reading, to keep the number of
+            // pending results bounded.
+            for (seq, sentence) in sentences.into_iter().enumerate() {
+                if job_tx.send((seq, sentence?)).is_err() {
+                    // All workers stopped, the panic is reported when the
+                    // scope ends.
+                    break;
+                }
+
+                while let Ok((seq, result)) = result_rx.try_recv() {
+                    if !receive(seq, result)? {
+                        return Ok(());
+                    }
+                }
+            }
+            drop(job_tx);
+
+            for (seq, result) in result_rx.iter() {
+                if !receive(seq, result)? {
+                    return Ok(());
+                }
+            }
+
+            Ok(())
+        });
+
+        if let Some(payload) = panic {
+            panic::resume_unwind(payload);
+        }
+
+        result
+    }
+}
+
+/// Buffer that restores the input order of results.
+struct Reorder<T> {
+    next: usize,
+    pending: BTreeMap<usize, T>,
+}
+
+impl<T> Default for Reorder<T> {
+    fn default() -> Self {
+        Reorder {
+            next: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> Reorder<T> {
+    fn push(&mut self, seq: usize, result: T) {
+        self.pending.insert(seq, result);
+    }
+
+    /// Get the next result in input order, if it is available.
+    fn pop(&mut self) -> Option<T> {
+        let result = self.pending.remove(&self.next)?;
+        self.next += 1;
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use corpus::CorpusSentence;
+    use error::{Error, Result};
+    use test_util::sentence;
+
+    use super::Pipeline;
+
+    fn corpus(n: usize) -> Vec<Result<CorpusSentence>> {
+        (1..=n)
+            .map(|id| {
+                Ok(CorpusSentence {
+                    id,
+                    source: "-".to_owned(),
+                    sentence: sentence(&format!(
+                        "1  Peter{}    Peter  NE     NE     _  2  SUBJ
+                         2  schläft  schlafen  VVFIN  VVFIN  _  0  ROOT",
+                        id
+                    )),
+                    conllu: None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn results_are_in_input_order() {
+        for threads in &[1, 2, 4] {
+            let mut ids = Vec::new();
+
+            Pipeline::new(*threads)
+                .run(
+                    corpus(1000),
+                    |sentence, graph| {
+                        // Vary the processing time, so that results arrive
+                        // out of order.
+                        if sentence.id % 7 == 0 {
+                            ::std::thread::yield_now();
+                        }
+                        assert_eq!(graph.node_count(), 2);
+                        Ok(sentence.sentence[0].form().unwrap().to_owned())
+                    },
+                    |form| {
+                        ids.push(form?);
+                        Ok(())
+                    },
+                )
+                .unwrap();
+
+            let expected: Vec<_> = (1..=1000).map(|id| format!("Peter{}", id)).collect();
+            assert_eq!(ids, expected, "with {} threads", threads);
+        }
+    }
+
+    #[test]
+    fn extractor_errors_are_passed_to_the_sink() {
+        for threads in &[1, 3] {
+            let mut results = Vec::new();
+
+            Pipeline::new(*threads)
+                .run(
+                    corpus(10),
+                    |sentence, _| {
+                        if sentence.id % 3 == 0 {
+                            Err(Error::MissingPos { token: 1 })
+                        } else {
+                            Ok(sentence.id)
+                        }
+                    },
+                    |result| {
+                        results.push(result.map_err(|e| e.to_string()));
+                        Ok(())
+                    },
+                )
+                .unwrap();
+
+            assert_eq!(results.len(), 10);
+            assert_eq!(results[0], Ok(1));
+            assert_eq!(
+                results[2],
+                Err("sentence 3: token 1 does not have a part-of-speech tag".to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn sink_errors_stop_the_pipeline() {
+        for threads in &[1, 3] {
+            let mut n_results = 0;
+
+            let result = Pipeline::new(*threads).run(
+                corpus(1000),
+                |sentence, _| Ok(sentence.id),
+                |id| {
+                    n_results += 1;
+                    if id? == 5 {
+                        Err(io::Error::other("stop").into())
+                    } else {
+                        Ok(())
+                    }
+                },
+            );
+
+            assert_eq!(n_results, 5);
+            match result {
+                Err(Error::Io(ref err)) => assert_eq!(err.to_string(), "stop"),
+                _ => panic!("expected the sink error, got {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn read_errors_stop_the_pipeline() {
+        let mut sentences = corpus(10);
+        sentences[4] = Err(io::Error::other("unreadable").into());
+
+        let result = Pipeline::new(2).run(sentences, |sentence, _| Ok(sentence.id), |_| Ok(()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "extractor panic")]
+    fn worker_panics_are_propagated() {
+        let _ = Pipeline::new(2).run(
+            corpus(1000),
+            |sentence, _| {
+                if sentence.id == 10 {
+                    panic!("extractor panic");
+                }
+                Ok(())
+            },
+            |_| Ok(()),
+        );
+    }
+}
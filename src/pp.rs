@@ -0,0 +1,154 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::EdgeDirection;
+
+use graph::{DependencyEdge, DependencyGraph};
+use scheme::{AnnotationScheme, PpAnalysis};
+
+/// A prepositional phrase.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PrepositionalPhrase {
+    /// The token that the PP is attached to.
+    pub head: NodeIndex,
+
+    /// The preposition. For multiword prepositions, this is the first
+    /// word.
+    pub preposition: NodeIndex,
+
+    /// The other words of a multiword preposition, in sentence order.
+    pub fixed: Vec<NodeIndex>,
+
+    /// The complement of the preposition.
+    pub complement: Option<NodeIndex>,
+}
+
+/// Find the prepositional phrases in a graph.
+///
+/// PPs are found using the relations and PP analysis of the annotation
+/// scheme. In the function-head analysis, a PP is a PP relation with a
+/// complement relation from the preposition to its complement. In the
+/// content-head analysis, a PP is a PP relation to a nominal that has a
+/// case dependent. Attachments without a case dependent are not PPs in
+/// the content-head analysis.
+pub fn prepositional_phrases(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+) -> Vec<PrepositionalPhrase> {
+    let mut pps = Vec::new();
+
+    for edge in graph.raw_edges() {
+        match edge.weight {
+            DependencyEdge::Relation(Some(relation)) if scheme.is_pp_relation(relation) => (),
+            _ => continue,
+        }
+
+        let (preposition, complement) = match scheme.pp_analysis {
+            PpAnalysis::FunctionHead => (
+                edge.target(),
+                preposition_complement(graph, scheme, edge.target()),
+            ),
+            PpAnalysis::ContentHead => (
+                ok_or_continue!(first_dependent(graph, edge.target(), |relation| {
+                    scheme.is_case_relation(relation)
+                })),
+                Some(edge.target()),
+            ),
+        };
+
+        let mut fixed: Vec<_> = graph
+            .edges_directed(preposition, EdgeDirection::Outgoing)
+            .filter(|e| match *e.weight() {
+                DependencyEdge::Relation(Some(relation)) => scheme.is_fixed_relation(relation),
+                _ => false,
+            })
+            .map(|e| e.target())
+            .collect();
+        fixed.sort_by_key(|&idx| graph[idx].offset);
+
+        pps.push(PrepositionalPhrase {
+            head: edge.source(),
+            preposition,
+            fixed,
+            complement,
+        });
+    }
+
+    pps
+}
+
+/// Find the complement of a preposition in the function-head analysis.
+///
+/// If the preposition has several dependents with a complement relation,
+/// such as the determiner and the noun in TIGER (NK), the first dependent
+/// with a noun tag is the complement. If none of them has a noun tag, the
+/// last dependent is the complement.
+pub fn preposition_complement(
+    graph: &DependencyGraph,
+    scheme: &AnnotationScheme,
+    preposition: NodeIndex,
+) -> Option<NodeIndex> {
+    let mut complements: Vec<_> = graph
+        .edges_directed(preposition, EdgeDirection::Outgoing)
+        .filter(|e| match *e.weight() {
+            DependencyEdge::Relation(Some(relation)) => {
+                scheme.is_prep_complement_relation(relation)
+            }
+            _ => false,
+        })
+        .map(|e| e.target())
+        .collect();
+    complements.sort_by_key(|&idx| graph[idx].offset);
+
+    complements
+        .iter()
+        .cloned()
+        .find(|&idx| match scheme.tag(graph[idx].token) {
+            Some(pos) => scheme.is_noun_tag(pos),
+            None => false,
+        })
+        .or_else(|| complements.last().cloned())
+}
+
+/// Find the leftmost dependent with a matching relation.
+fn first_dependent<F>(graph: &DependencyGraph, idx: NodeIndex, matches: F) -> Option<NodeIndex>
+where
+    F: Fn(&str) -> bool,
+{
+    graph
+        .edges_directed(idx, EdgeDirection::Outgoing)
+        .filter(|e| match *e.weight() {
+            DependencyEdge::Relation(Some(relation)) => matches(relation),
+            _ => false,
+        })
+        .map(|e| e.target())
+        .min_by_key(|&idx| graph[idx].offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::prepositional_phrases;
+    use graph::sentence_to_graph;
+    use scheme::AnnotationScheme;
+    use test_util::sentence;
+
+    #[test]
+    fn tiger_complement_is_nominal() {
+        let scheme = AnnotationScheme::preset("tiger").unwrap();
+        let sentence = sentence(
+            "1  Er         er         PPER   PPER   _  2  SB
+             2  schneidet  schneiden  VVFIN  VVFIN  _  0  --
+             3  mit        mit        APPR   APPR   _  2  MO
+             4  dem        der        ART    ART    _  3  NK
+             5  Messer     Messer     NN     NN     _  3  NK",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+
+        let pps = prepositional_phrases(&graph, &scheme);
+        assert_eq!(pps.len(), 1);
+        assert_eq!(pps[0].head, NodeIndex::new(1));
+        assert_eq!(pps[0].preposition, NodeIndex::new(2));
+        assert_eq!(pps[0].complement, Some(NodeIndex::new(4)));
+    }
+}
@@ -0,0 +1,684 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::EdgeDirection;
+use regex::Regex;
+
+use graph::{ancestor_tokens, DependencyEdge, DependencyGraph};
+use topo::feature_value;
+
+/// Error that occurred while parsing a pattern.
+#[derive(Debug, Eq, PartialEq)]
+pub struct PatternError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid pattern at character {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl Error for PatternError {}
+
+#[derive(Debug)]
+enum Value {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl Value {
+    fn matches(&self, value: &str) -> bool {
+        match *self {
+            Value::Literal(ref literal) => literal == value,
+            Value::Regex(ref re) => re.is_match(value),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Attribute {
+    Form,
+    Lemma,
+    CPos,
+    Pos,
+    Relation,
+    Feature(String),
+}
+
+#[derive(Debug)]
+struct Constraint {
+    attribute: Attribute,
+    value: Value,
+    negated: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Direction {
+    Governs,
+    GovernedBy,
+    Dominates,
+    DominatedBy,
+}
+
+#[derive(Debug)]
+struct Relation {
+    direction: Direction,
+    label: Option<Value>,
+}
+
+#[derive(Debug)]
+struct PatternNode {
+    id: usize,
+    constraints: Vec<Constraint>,
+    steps: Vec<(Relation, PatternNode)>,
+}
+
+/// A compiled dependency graph pattern.
+///
+/// A pattern is a sequence of node descriptions that are connected by
+/// relations. For example:
+///
+/// ```text
+/// [pos=/^V/]=verb >PP []=prep >PN [pos=NN]=obj
+/// ```
+///
+/// matches a verb that governs a preposition through the *PP* relation,
+/// where the preposition governs a common noun through the *PN* relation.
+///
+/// A node description is a list of `&`-separated constraints between
+/// brackets. A constraint has the form `attr=value` or `attr!=value`,
+/// where the attribute is one of `form`, `lemma`, `cpos`, `pos`, `rel`
+/// (the label of the incoming relation), or the name of a feature. The
+/// value is a bare word, a quoted string, or a regular expression between
+/// slashes. The empty description `[]` matches any node. A node can be
+/// bound to a name by appending `=name`.
+///
+/// The following relations are supported, optionally followed by a
+/// label or a label regular expression:
+///
+/// * `A >REL B`: *A* governs *B*;
+/// * `A <REL B`: *A* is governed by *B*;
+/// * `A >> B`: *A* dominates *B*;
+/// * `A << B`: *A* is dominated by *B*.
+///
+/// Relations chain from left to right, each relation applies to the node
+/// that precedes it. Parentheses start a branch, so that several
+/// relations can apply to the same node:
+///
+/// ```text
+/// [pos=/^V/]=verb (>OBJA []=obj) >PP [lemma=mit]
+/// ```
+///
+/// Matches are injective: a token is matched by at most one node of the
+/// pattern. The virtual root of a graph is never matched.
+#[derive(Debug)]
+pub struct Pattern {
+    root: PatternNode,
+    names: Vec<Option<String>>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Pattern, PatternError> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            names: Vec::new(),
+        };
+
+        let root = parser.parse_pattern()?;
+
+        parser.skip_whitespace();
+        if parser.pos != parser.chars.len() {
+            return Err(parser.error("expected a relation or the end of the pattern"));
+        }
+
+        Ok(Pattern {
+            root,
+            names: parser.names,
+        })
+    }
+
+    /// The names of the nodes in the pattern, in pattern order.
+    pub fn names(&self) -> &[Option<String>] {
+        &self.names
+    }
+
+    /// Find all matches of the pattern in a graph.
+    pub fn matches(&self, graph: &DependencyGraph) -> Vec<Match> {
+        let mut matches = Vec::new();
+
+        for idx in graph.node_indices() {
+            let partial = vec![None; self.names.len()];
+            for nodes in match_node(graph, &self.root, idx, partial) {
+                matches.push(Match {
+                    nodes: nodes
+                        .into_iter()
+                        .map(|idx| idx.expect("unbound pattern node"))
+                        .collect(),
+                    names: self
+                        .names
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(id, name)| name.clone().map(|name| (name, id)))
+                        .collect(),
+                });
+            }
+        }
+
+        matches
+    }
+}
+
+/// A match of a pattern in a dependency graph.
+#[derive(Clone, Debug)]
+pub struct Match {
+    nodes: Vec<NodeIndex>,
+    names: HashMap<String, usize>,
+}
+
+impl Match {
+    /// The node that was matched by the first node of the pattern.
+    pub fn root(&self) -> NodeIndex {
+        self.nodes[0]
+    }
+
+    /// Get the node that is bound to `name`.
+    pub fn get(&self, name: &str) -> Option<NodeIndex> {
+        self.names.get(name).map(|&id| self.nodes[id])
+    }
+
+    /// The matched nodes, in pattern order.
+    pub fn nodes(&self) -> &[NodeIndex] {
+        &self.nodes
+    }
+}
+
+fn match_node(
+    graph: &DependencyGraph,
+    pattern: &PatternNode,
+    idx: NodeIndex,
+    mut partial: Vec<Option<NodeIndex>>,
+) -> Vec<Vec<Option<NodeIndex>>> {
+    if graph[idx].is_root() || partial.contains(&Some(idx)) {
+        return Vec::new();
+    }
+
+    if !pattern
+        .constraints
+        .iter()
+        .all(|c| constraint_matches(graph, idx, c))
+    {
+        return Vec::new();
+    }
+
+    partial[pattern.id] = Some(idx);
+    let mut results = vec![partial];
+
+    for (relation, child) in &pattern.steps {
+        let mut extended = Vec::new();
+
+        for candidate in related_nodes(graph, idx, relation) {
+            for result in &results {
+                extended.extend(match_node(graph, child, candidate, result.clone()));
+            }
+        }
+
+        results = extended;
+        if results.is_empty() {
+            break;
+        }
+    }
+
+    results
+}
+
+fn constraint_matches(graph: &DependencyGraph, idx: NodeIndex, constraint: &Constraint) -> bool {
+    let token = graph[idx].token;
+
+    let value = match constraint.attribute {
+        Attribute::Form => token.form().map(|v| constraint.value.matches(v)),
+        Attribute::Lemma => token.lemma().map(|v| constraint.value.matches(v)),
+        Attribute::CPos => token.cpos().map(|v| constraint.value.matches(v)),
+        Attribute::Pos => token.pos().map(|v| constraint.value.matches(v)),
+        Attribute::Relation => incoming_label(graph, idx).map(|v| constraint.value.matches(v)),
+        Attribute::Feature(ref feature) => {
+            feature_value(token, feature).map(|v| constraint.value.matches(&v))
+        }
+    };
+
+    // A missing value never matches, not even in a negated constraint.
+    match value {
+        Some(matches) => matches != constraint.negated,
+        None => false,
+    }
+}
+
+fn incoming_label<'a>(graph: &DependencyGraph<'a>, idx: NodeIndex) -> Option<&'a str> {
+    graph
+        .edges_directed(idx, EdgeDirection::Incoming)
+        .filter_map(|e| e.weight().label())
+        .next()
+}
+
+fn related_nodes(graph: &DependencyGraph, idx: NodeIndex, relation: &Relation) -> Vec<NodeIndex> {
+    match relation.direction {
+        Direction::Governs => labeled_edges(graph, idx, EdgeDirection::Outgoing, &relation.label),
+        Direction::GovernedBy => {
+            labeled_edges(graph, idx, EdgeDirection::Incoming, &relation.label)
+        }
+        Direction::Dominates => {
+            let mut seen = HashSet::new();
+            let mut agenda = vec![idx];
+            let mut descendants = Vec::new();
+
+            while let Some(idx) = agenda.pop() {
+                for dep in labeled_edges(graph, idx, EdgeDirection::Outgoing, &None) {
+                    if seen.insert(dep) {
+                        descendants.push(dep);
+                        agenda.push(dep);
+                    }
+                }
+            }
+
+            descendants
+        }
+        Direction::DominatedBy => ancestor_tokens(graph, idx).collect(),
+    }
+}
+
+fn labeled_edges(
+    graph: &DependencyGraph,
+    idx: NodeIndex,
+    direction: EdgeDirection,
+    label: &Option<Value>,
+) -> Vec<NodeIndex> {
+    graph
+        .edges_directed(idx, direction)
+        .filter(|e| match *e.weight() {
+            DependencyEdge::Relation(rel) | DependencyEdge::Root(rel) => match *label {
+                Some(ref label) => rel.map(|rel| label.matches(rel)).unwrap_or(false),
+                None => true,
+            },
+            DependencyEdge::Precedence => false,
+        })
+        .map(|e| match direction {
+            EdgeDirection::Outgoing => e.target(),
+            EdgeDirection::Incoming => e.source(),
+        })
+        .collect()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    names: Vec<Option<String>>,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> PatternError {
+        PatternError {
+            position: self.pos,
+            message: message.to_owned(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map(char::is_whitespace).unwrap_or(false) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), PatternError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn parse_pattern(&mut self) -> Result<PatternNode, PatternError> {
+        let mut node = self.parse_node()?;
+        self.parse_steps(&mut node)?;
+        Ok(node)
+    }
+
+    fn parse_steps(&mut self, node: &mut PatternNode) -> Result<(), PatternError> {
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some('(') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    let relation = self.parse_relation()?;
+                    let child = self.parse_pattern()?;
+                    self.skip_whitespace();
+                    self.expect(')')?;
+                    node.steps.push((relation, child));
+                }
+                Some('<') | Some('>') => {
+                    let relation = self.parse_relation()?;
+                    let child = self.parse_pattern()?;
+                    node.steps.push((relation, child));
+
+                    // The remainder of the chain applies to the child.
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn parse_relation(&mut self) -> Result<Relation, PatternError> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("expected a relation"))?;
+        self.pos += 1;
+
+        let doubled = self.peek() == Some(c);
+        if doubled {
+            self.pos += 1;
+        }
+
+        let direction = match (c, doubled) {
+            ('>', false) => Direction::Governs,
+            ('<', false) => Direction::GovernedBy,
+            ('>', true) => Direction::Dominates,
+            ('<', true) => Direction::DominatedBy,
+            _ => return Err(self.error("expected a relation")),
+        };
+
+        let label = match self.peek() {
+            Some(c) if c.is_whitespace() || c == '[' => None,
+            None => None,
+            Some(_) if doubled => {
+                return Err(self.error("dominance relations cannot have a label"));
+            }
+            Some(_) => Some(self.parse_value()?),
+        };
+
+        Ok(Relation { direction, label })
+    }
+
+    fn parse_node(&mut self) -> Result<PatternNode, PatternError> {
+        self.skip_whitespace();
+        self.expect('[')?;
+
+        let mut constraints = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() != Some(']') {
+            loop {
+                constraints.push(self.parse_constraint()?);
+                self.skip_whitespace();
+
+                if self.peek() == Some('&') {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        self.expect(']')?;
+
+        let name = if self.peek() == Some('=') {
+            self.pos += 1;
+            let name = self.parse_word();
+            if name.is_empty() {
+                return Err(self.error("expected a node name"));
+            }
+            if self.names.iter().any(|n| n.as_ref() == Some(&name)) {
+                return Err(self.error(&format!("duplicate node name: {}", name)));
+            }
+            Some(name)
+        } else {
+            None
+        };
+
+        let id = self.names.len();
+        self.names.push(name);
+
+        Ok(PatternNode {
+            id,
+            constraints,
+            steps: Vec::new(),
+        })
+    }
+
+    fn parse_constraint(&mut self) -> Result<Constraint, PatternError> {
+        self.skip_whitespace();
+
+        let attribute = match self.parse_word().as_str() {
+            "" => return Err(self.error("expected an attribute")),
+            "form" => Attribute::Form,
+            "lemma" => Attribute::Lemma,
+            "cpos" => Attribute::CPos,
+            "pos" => Attribute::Pos,
+            "rel" => Attribute::Relation,
+            feature => Attribute::Feature(feature.to_owned()),
+        };
+
+        self.skip_whitespace();
+        let negated = if self.peek() == Some('!') {
+            self.pos += 1;
+            true
+        } else {
+            false
+        };
+        self.expect('=')?;
+        self.skip_whitespace();
+
+        let value = self.parse_value()?;
+
+        Ok(Constraint {
+            attribute,
+            value,
+            negated,
+        })
+    }
+
+    fn parse_value(&mut self) -> Result<Value, PatternError> {
+        match self.peek() {
+            Some('/') => {
+                let start = self.pos;
+                let re = self.parse_delimited('/')?;
+                Regex::new(&re).map(Value::Regex).map_err(|e| PatternError {
+                    position: start,
+                    message: format!("invalid regular expression: {}", e),
+                })
+            }
+            Some('"') => Ok(Value::Literal(self.parse_delimited('"')?)),
+            _ => {
+                let word = self.parse_word();
+                if word.is_empty() {
+                    Err(self.error("expected a value"))
+                } else {
+                    Ok(Value::Literal(word))
+                }
+            }
+        }
+    }
+
+    fn parse_delimited(&mut self, delim: char) -> Result<String, PatternError> {
+        self.expect(delim)?;
+
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some('\\') if self.chars.get(self.pos + 1) == Some(&delim) => {
+                    value.push(delim);
+                    self.pos += 2;
+                }
+                Some(c) if c == delim => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+                None => return Err(self.error(&format!("unterminated value, expected '{}'", delim))),
+            }
+        }
+    }
+
+    fn parse_word(&mut self) -> String {
+        let mut word = String::new();
+
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || "[]()<>=!&/\"".contains(c) {
+                break;
+            }
+
+            word.push(c);
+            self.pos += 1;
+        }
+
+        word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+
+    use super::{Pattern, PatternError};
+    use graph::{sentence_to_graph, DependencyGraph};
+    use test_util::sentence;
+
+    static SENTENCE: &str = "
+        1  Peter      Peter      NE     NE     tf:VF  2  SUBJ
+        2  schneidet  schneiden  VVFIN  VVFIN  tf:LK  0  ROOT
+        3  das        der        ART    ART    tf:MF  4  DET
+        4  Brot       Brot       NN     NN     tf:MF  2  OBJA
+        5  mit        mit        APPR   APPR   tf:MF  2  PP
+        6  dem        der        ART    ART    tf:MF  7  DET
+        7  Messer     Messer     NN     NN     tf:MF  5  PN
+        8  .          .          $.     $.     _      2  -PUNCT-";
+
+    fn matches(pattern: &str, root: bool) -> Vec<Vec<usize>> {
+        let sentence = sentence(SENTENCE);
+        let graph = sentence_to_graph(&sentence, false, root).unwrap();
+        matches_in(&graph, pattern)
+    }
+
+    /// The matched nodes, sorted by the token offsets.
+    fn matches_in(graph: &DependencyGraph, pattern: &str) -> Vec<Vec<usize>> {
+        let mut matches: Vec<Vec<usize>> = Pattern::parse(pattern)
+            .unwrap()
+            .matches(graph)
+            .iter()
+            .map(|m| m.nodes().iter().map(|idx| idx.index()).collect())
+            .collect();
+        matches.sort();
+        matches
+    }
+
+    fn parse_error(pattern: &str) -> PatternError {
+        Pattern::parse(pattern).expect_err("pattern should not parse")
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse_error("[pos=NN").position, 7);
+        assert_eq!(parse_error("[pos=NN").message, "expected ']'");
+        assert_eq!(parse_error("[] >").position, 4);
+        assert_eq!(parse_error("[] []").position, 3);
+        assert_eq!(parse_error("[pos=]").message, "expected a value");
+        assert_eq!(parse_error("[=NN]").message, "expected an attribute");
+        assert_eq!(parse_error("[lemma=\"mit]").message, "unterminated value, expected '\"'");
+        assert_eq!(
+            parse_error("[]=a >OBJA []=a").message,
+            "duplicate node name: a"
+        );
+        assert_eq!(
+            parse_error("[] >>PP []").message,
+            "dominance relations cannot have a label"
+        );
+
+        let error = parse_error("[pos=/(/]");
+        assert_eq!(error.position, 5);
+        assert!(error.message.starts_with("invalid regular expression"));
+    }
+
+    #[test]
+    fn constraints() {
+        assert_eq!(matches("[pos=NN]", false), vec![vec![3], vec![6]]);
+        assert_eq!(matches("[lemma=\"der\"]", false), vec![vec![2], vec![5]]);
+        assert_eq!(matches("[pos=/^N/ & form!=Brot]", false), vec![vec![0], vec![6]]);
+        assert_eq!(matches("[rel=/^OBJ/]", false), vec![vec![3]]);
+        assert_eq!(matches("[tf=VF]", false), vec![vec![0]]);
+
+        // Tokens without the feature do not match negated constraints.
+        assert_eq!(matches("[tf!=MF]", false), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn relations() {
+        assert_eq!(matches("[pos=VVFIN] >OBJA []", false), vec![vec![1, 3]]);
+        assert_eq!(matches("[pos=VVFIN] >/^OBJ|PP$/ []", false), vec![vec![1, 3], vec![1, 4]]);
+        assert_eq!(matches("[pos=NN] <PN [] <PP []", false), vec![vec![6, 4, 1]]);
+        assert!(matches("[pos=NN] <OBJA [pos=APPR]", false).is_empty());
+    }
+
+    #[test]
+    fn branching() {
+        let sentence = sentence(SENTENCE);
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let pattern =
+            Pattern::parse("[pos=VVFIN]=verb (>SUBJ []=subj) >PP []=prep >PN []=obj").unwrap();
+
+        let matches = pattern.matches(&graph);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].root(), NodeIndex::new(1));
+        assert_eq!(matches[0].get("subj"), Some(NodeIndex::new(0)));
+        assert_eq!(matches[0].get("prep"), Some(NodeIndex::new(4)));
+        assert_eq!(matches[0].get("obj"), Some(NodeIndex::new(6)));
+        assert_eq!(matches[0].get("verb"), Some(NodeIndex::new(1)));
+        assert_eq!(matches[0].get("other"), None);
+
+        // All branches must match.
+        assert!(matches_in(&graph, "[pos=VVFIN] (>SUBJ []) >OBJD []").is_empty());
+    }
+
+    #[test]
+    fn dominance() {
+        assert_eq!(
+            matches("[pos=VVFIN] >> [pos=NN]", false),
+            vec![vec![1, 3], vec![1, 6]]
+        );
+        assert_eq!(matches("[pos=ART] << [pos=APPR]", false), vec![vec![5, 4]]);
+        assert!(matches("[pos=APPR] >> [form=Brot]", false).is_empty());
+    }
+
+    #[test]
+    fn matches_are_injective() {
+        assert_eq!(
+            matches("[pos=VVFIN] (>> [pos=NN]) >> [pos=NN]", false),
+            vec![vec![1, 3, 6], vec![1, 6, 3]]
+        );
+        assert!(matches("[pos=APPR] >PN [] << [pos=APPR]", false).is_empty());
+    }
+
+    #[test]
+    fn virtual_root_is_not_matched() {
+        assert_eq!(matches("[]", true).len(), 8);
+        assert!(matches("[pos=VVFIN] < []", true).is_empty());
+        assert_eq!(matches("[form=Messer] << []", true), vec![vec![6, 1], vec![6, 4]]);
+    }
+}
@@ -0,0 +1,267 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use backoff::AttachmentCounts;
+use instance::{InstanceCandidate, PpInstance};
+use scheme::AnnotationScheme;
+
+/// Order candidates by their distance to the preposition. Of two
+/// candidates at the same distance, the preceding candidate comes first.
+fn closest_order(a: &InstanceCandidate, b: &InstanceCandidate) -> Ordering {
+    (a.distance.abs(), a.distance).cmp(&(b.distance.abs(), b.distance))
+}
+
+fn ranking<F>(instance: &PpInstance, order: F) -> Vec<usize>
+where
+    F: Fn(&InstanceCandidate, &InstanceCandidate) -> Ordering,
+{
+    let mut indices: Vec<_> = (0..instance.candidates.len()).collect();
+    indices.sort_by(|&a, &b| order(&instance.candidates[a], &instance.candidates[b]));
+    indices
+}
+
+/// Rank candidates by decreasing score, ties are ranked by distance.
+/// Candidates without a score are ranked last.
+pub fn score_ranking(instance: &PpInstance) -> Vec<usize> {
+    ranking(instance, |a, b| {
+        let score_a = a.score.unwrap_or(f64::NEG_INFINITY);
+        let score_b = b.score.unwrap_or(f64::NEG_INFINITY);
+
+        score_b
+            .partial_cmp(&score_a)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| closest_order(a, b))
+    })
+}
+
+/// Rank candidates by distance to the preposition.
+pub fn closest_ranking(instance: &PpInstance) -> Vec<usize> {
+    ranking(instance, closest_order)
+}
+
+/// Rank candidates by right association (Kimball, 1973): preceding
+/// candidates come first, the most recent candidate first.
+pub fn right_association_ranking(instance: &PpInstance) -> Vec<usize> {
+    ranking(instance, |a, b| {
+        (a.distance > 0, a.distance.abs()).cmp(&(b.distance > 0, b.distance.abs()))
+    })
+}
+
+/// Rank verb candidates before noun candidates, ties are ranked by
+/// distance.
+pub fn verb_ranking(instance: &PpInstance, scheme: &AnnotationScheme) -> Vec<usize> {
+    ranking(instance, |a, b| {
+        let verb_a = scheme.is_verb_tag(&a.token.pos);
+        let verb_b = scheme.is_verb_tag(&b.token.pos);
+
+        verb_b.cmp(&verb_a).then_with(|| closest_order(a, b))
+    })
+}
+
+/// Attachment probabilities of candidate ranks.
+///
+/// The rank of a candidate is its position among the candidates on the
+/// same side of the preposition, see `InstanceCandidate::rank`.
+#[derive(Default)]
+pub struct RankPrior {
+    counts: HashMap<isize, AttachmentCounts>,
+}
+
+impl RankPrior {
+    pub fn new() -> Self {
+        RankPrior::default()
+    }
+
+    /// Count the candidate ranks of an instance.
+    pub fn add(&mut self, instance: &PpInstance) {
+        for candidate in &instance.candidates {
+            let counts = self.counts.entry(candidate.rank).or_default();
+            counts.total += 1;
+            if candidate.head {
+                counts.attached += 1;
+            }
+        }
+    }
+
+    /// The probability that a candidate with the given rank is attached.
+    pub fn probability(&self, rank: isize) -> f64 {
+        match self.counts.get(&rank) {
+            Some(counts) if counts.total > 0 => counts.attached as f64 / counts.total as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Rank candidates by decreasing attachment probability of their
+    /// rank, ties are ranked by distance.
+    pub fn ranking(&self, instance: &PpInstance) -> Vec<usize> {
+        ranking(instance, |a, b| {
+            self.probability(b.rank)
+                .partial_cmp(&self.probability(a.rank))
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| closest_order(a, b))
+        })
+    }
+}
+
+/// Accuracy@k and mean reciprocal rank of candidate rankings.
+#[derive(Clone, Debug)]
+pub struct RankingMetrics {
+    instances: usize,
+    correct_at: Vec<usize>,
+    reciprocal_rank: f64,
+}
+
+impl RankingMetrics {
+    /// Construct metrics that record accuracy up to `max_k`.
+    pub fn new(max_k: usize) -> Self {
+        RankingMetrics {
+            instances: 0,
+            correct_at: vec![0; max_k],
+            reciprocal_rank: 0.0,
+        }
+    }
+
+    /// Add a ranking of the candidates of an instance.
+    pub fn add(&mut self, instance: &PpInstance, ranking: &[usize]) {
+        self.instances += 1;
+
+        let gold = match instance.head() {
+            Some(gold) => gold,
+            None => return,
+        };
+
+        if let Some(position) = ranking.iter().position(|&idx| idx == gold) {
+            for correct in self.correct_at.iter_mut().skip(position) {
+                *correct += 1;
+            }
+
+            self.reciprocal_rank += 1.0 / (position + 1) as f64;
+        }
+    }
+
+    /// The largest k for which accuracy@k is recorded.
+    pub fn max_k(&self) -> usize {
+        self.correct_at.len()
+    }
+
+    /// The number of instances.
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+
+    /// The fraction of instances where the head is in the first `k`
+    /// candidates, 0 if there are no instances.
+    pub fn accuracy_at(&self, k: usize) -> f64 {
+        assert!(
+            k > 0 && k <= self.correct_at.len(),
+            "accuracy@{} is not recorded",
+            k
+        );
+
+        if self.instances == 0 {
+            return 0.0;
+        }
+
+        self.correct_at[k - 1] as f64 / self.instances as f64
+    }
+
+    /// Mean reciprocal rank of the head, 0 if there are no instances.
+    pub fn mean_reciprocal_rank(&self) -> f64 {
+        if self.instances == 0 {
+            return 0.0;
+        }
+
+        self.reciprocal_rank / self.instances as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use instance::{InstanceFormat, PpInstance};
+    use scheme::AnnotationScheme;
+
+    use super::{closest_ranking, right_association_ranking, score_ranking, verb_ranking,
+                RankPrior, RankingMetrics};
+
+    /// An instance with candidates at distances -3, -1, 1, and 3, where
+    /// the first candidate is the head.
+    fn instance() -> PpInstance {
+        PpInstance::parse(
+            "1 mit APPR MF Messer NN MF \
+             schneidet VVFIN LK -3 -2 1 \
+             Brot NN MF -1 -1 0 \
+             Tisch NN MF 1 1 0 \
+             liegt VVFIN VC 3 2 0",
+            InstanceFormat::default(),
+        ).unwrap()
+    }
+
+    #[test]
+    fn closest_prefers_preceding_candidates() {
+        assert_eq!(closest_ranking(&instance()), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn right_association() {
+        assert_eq!(right_association_ranking(&instance()), vec![1, 0, 2, 3]);
+    }
+
+    #[test]
+    fn verbs_before_nouns() {
+        let scheme = AnnotationScheme::default();
+        assert_eq!(verb_ranking(&instance(), &scheme), vec![0, 3, 1, 2]);
+    }
+
+    #[test]
+    fn scores_with_ties_and_missing_scores() {
+        let mut instance = instance();
+        instance.candidates[0].score = Some(0.2);
+        instance.candidates[1].score = Some(0.9);
+        instance.candidates[2].score = Some(0.2);
+
+        assert_eq!(score_ranking(&instance), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn rank_prior() {
+        let mut prior = RankPrior::new();
+        prior.add(&instance());
+
+        let mut other = instance();
+        other.candidates[0].head = false;
+        other.candidates[2].head = true;
+        prior.add(&other);
+
+        assert_eq!(prior.probability(-2), 0.5);
+        assert_eq!(prior.probability(1), 0.5);
+        assert_eq!(prior.probability(-1), 0.0);
+        assert_eq!(prior.probability(5), 0.0);
+
+        // Ties are ranked by distance.
+        assert_eq!(prior.ranking(&instance()), vec![2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn metrics() {
+        let instance = instance();
+        let mut no_head = instance.clone();
+        no_head.candidates[0].head = false;
+
+        let mut metrics = RankingMetrics::new(2);
+        assert_eq!(metrics.accuracy_at(1), 0.0);
+        assert_eq!(metrics.mean_reciprocal_rank(), 0.0);
+
+        // The head is ranked first, second, third, and there is no head.
+        metrics.add(&instance, &[0, 1, 2, 3]);
+        metrics.add(&instance, &right_association_ranking(&instance));
+        metrics.add(&instance, &closest_ranking(&instance));
+        metrics.add(&no_head, &[0, 1, 2, 3]);
+
+        assert_eq!(metrics.instances(), 4);
+        assert_eq!(metrics.max_k(), 2);
+        assert_eq!(metrics.accuracy_at(1), 0.25);
+        assert_eq!(metrics.accuracy_at(2), 0.5);
+        let mrr = (1.0 + 1.0 / 2.0 + 1.0 / 3.0) / 4.0;
+        assert!((metrics.mean_reciprocal_rank() - mrr).abs() < 1e-9);
+    }
+}
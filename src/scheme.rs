@@ -0,0 +1,490 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use conllx::Token;
+use toml;
+
+use error::{Error, Result};
+
+/// The names of the built-in annotation schemes.
+pub static PRESETS: &[&str] = &["tueba-dz", "tiger", "ud"];
+
+/// An annotation scheme.
+///
+/// The annotation scheme describes the dependency relations, part-of-speech
+/// tags, and topological fields that extractors look for. Schemes can be
+/// read from TOML files, fields that are not specified in a file get the
+/// value of the TüBa-D/Z scheme:
+///
+/// ```toml
+/// pp_analysis = "function-head"
+///
+/// [relations]
+/// pp = ["PP"]
+/// prep_complement = ["PN"]
+/// auxiliary = ["AUX"]
+/// excluded = []
+///
+/// [tags]
+/// coarse = false
+/// noun_prefixes = ["N"]
+/// verb_prefixes = ["V"]
+/// finite_verbs = ["VVFIN", "VAFIN", "VMFIN"]
+/// complementizers = ["KOUS", "KOUI"]
+/// punctuation_prefixes = ["$"]
+///
+/// [topological_fields]
+/// feature = "tf"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnnotationScheme {
+    pub pp_analysis: PpAnalysis,
+    pub relations: Relations,
+    pub tags: Tags,
+    pub topological_fields: TopologicalFields,
+}
+
+/// The analysis of prepositional phrases.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PpAnalysis {
+    /// The preposition heads the PP and is attached with a PP relation,
+    /// its complement is attached with a complement relation (TüBa-D/Z).
+    #[default]
+    FunctionHead,
+
+    /// The nominal heads the PP and is attached with a PP relation, the
+    /// preposition is attached to the nominal with a case relation
+    /// (Universal Dependencies).
+    ContentHead,
+}
+
+/// Dependency relations of an annotation scheme.
+///
+/// Relations with a subtype, such as `obl:arg`, also match the relation
+/// without the subtype, unless the relation with the subtype is excluded.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Relations {
+    /// Attachment of a preposition to its head.
+    pub pp: Vec<String>,
+
+    /// Attachment of the complement of a preposition.
+    pub prep_complement: Vec<String>,
+
+    /// Attachment of a verb to an auxiliary or modal verb.
+    pub auxiliary: Vec<String>,
+
+    /// Attachment of an auxiliary or modal verb to the verb that it
+    /// governs, as in Universal Dependencies.
+    pub auxiliary_dependent: Vec<String>,
+
+    /// Attachment of complements that are part of the verb chain if they
+    /// are non-finite verbs, such as the clausal and verbal objects of
+    /// TIGER (OC).
+    pub verb_complement: Vec<String>,
+
+    /// Attachment of a preposition to its complement in the content-head
+    /// analysis.
+    pub case: Vec<String>,
+
+    /// Attachment of the other words of a multiword expression to its
+    /// first word.
+    pub fixed: Vec<String>,
+
+    /// Attachment of the arguments of a verb that are part of its
+    /// subcategorization frame.
+    pub arguments: Vec<String>,
+
+    /// Relations with a subtype that do not match the relation without
+    /// the subtype, such as possessives (`nmod:poss`) in Universal
+    /// Dependencies.
+    pub excluded: Vec<String>,
+}
+
+impl Default for Relations {
+    fn default() -> Self {
+        Relations {
+            pp: strings(&["PP"]),
+            prep_complement: strings(&["PN"]),
+            auxiliary: strings(&["AUX"]),
+            auxiliary_dependent: Vec::new(),
+            verb_complement: Vec::new(),
+            case: Vec::new(),
+            fixed: Vec::new(),
+            arguments: strings(&[
+                "SUBJ", "SUBJC", "OBJA", "OBJA2", "OBJD", "OBJG", "OBJP", "OBJC", "OBJI", "PRED",
+                "PP",
+            ]),
+            excluded: Vec::new(),
+        }
+    }
+}
+
+/// Part-of-speech tags of an annotation scheme.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tags {
+    /// Use the coarse-grained part-of-speech tags instead of the
+    /// fine-grained tags. In CoNLL-U, these are the universal
+    /// part-of-speech tags.
+    pub coarse: bool,
+
+    /// Prefixes of noun tags.
+    pub noun_prefixes: Vec<String>,
+
+    /// Prefixes of verb tags.
+    pub verb_prefixes: Vec<String>,
+
+    /// Tags of finite verbs.
+    pub finite_verbs: Vec<String>,
+
+    /// Tags of non-finite verbs.
+    pub nonfinite_verbs: Vec<String>,
+
+    /// Tags of prepositions, postpositions, and circumpositions.
+    pub prepositions: Vec<String>,
+
+    /// Tags of subordinating conjunctions.
+    pub complementizers: Vec<String>,
+
+    /// Tags of relative pronouns.
+    pub relatives: Vec<String>,
+
+    /// Tags of interrogative pronouns and adverbs.
+    pub interrogatives: Vec<String>,
+
+    /// Tags of coordinating conjunctions.
+    pub coordinations: Vec<String>,
+
+    /// Tags of separable verb particles and infinitival _zu_.
+    pub verb_particles: Vec<String>,
+
+    /// Prefixes of punctuation tags.
+    pub punctuation_prefixes: Vec<String>,
+}
+
+impl Default for Tags {
+    fn default() -> Self {
+        Tags {
+            coarse: false,
+            noun_prefixes: strings(&["N"]),
+            verb_prefixes: strings(&["V"]),
+            finite_verbs: strings(&["VVFIN", "VAFIN", "VMFIN"]),
+            nonfinite_verbs: strings(&[
+                "VVINF", "VVIZU", "VVPP", "VAINF", "VAPP", "VMINF", "VMPP",
+            ]),
+            prepositions: strings(&["APPR", "APPRART", "APPO", "APZR"]),
+            complementizers: strings(&["KOUS", "KOUI"]),
+            relatives: strings(&["PRELS", "PRELAT"]),
+            interrogatives: strings(&["PWS", "PWAT", "PWAV"]),
+            coordinations: strings(&["KON"]),
+            verb_particles: strings(&["PTKVZ", "PTKZU"]),
+            punctuation_prefixes: strings(&["$"]),
+        }
+    }
+}
+
+/// Topological fields of an annotation scheme.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TopologicalFields {
+    /// The token feature that stores the topological field.
+    pub feature: String,
+
+    /// Initial field (Vorfeld).
+    pub vf: String,
+
+    /// Middle field (Mittelfeld).
+    pub mf: String,
+
+    /// Final field (Nachfeld).
+    pub nf: String,
+
+    /// Left bracket (linke Klammer).
+    pub lk: String,
+
+    /// Verb complex, the right bracket.
+    pub vc: String,
+
+    /// Complementizer field.
+    pub c: String,
+
+    /// Field of coordinating conjunctions.
+    pub koord: String,
+
+    /// Left dislocation.
+    pub lv: String,
+
+    /// Field of paratactic conjunctions.
+    pub parord: String,
+
+    /// Tokens with an unknown field.
+    pub unknown: String,
+}
+
+impl Default for TopologicalFields {
+    fn default() -> Self {
+        TopologicalFields {
+            feature: "tf".to_owned(),
+            vf: "VF".to_owned(),
+            mf: "MF".to_owned(),
+            nf: "NF".to_owned(),
+            lk: "LK".to_owned(),
+            vc: "VC".to_owned(),
+            c: "C".to_owned(),
+            koord: "KOORD".to_owned(),
+            lv: "LV".to_owned(),
+            parord: "PARORD".to_owned(),
+            unknown: "UK".to_owned(),
+        }
+    }
+}
+
+impl AnnotationScheme {
+    /// Get a built-in annotation scheme.
+    ///
+    /// Returns `None` if there is no preset with the given name, see
+    /// `PRESETS` for the available presets.
+    pub fn preset(name: &str) -> Option<AnnotationScheme> {
+        match name {
+            "tueba-dz" => Some(AnnotationScheme::default()),
+            "tiger" => Some(AnnotationScheme {
+                relations: Relations {
+                    pp: strings(&["MO", "MNR", "OP", "PG", "SBP"]),
+                    prep_complement: strings(&["NK"]),
+                    auxiliary: Vec::new(),
+                    verb_complement: strings(&["OC"]),
+                    arguments: strings(&["SB", "SBP", "OA", "OA2", "DA", "OG", "OP", "OC", "PD"]),
+                    ..Relations::default()
+                },
+                ..AnnotationScheme::default()
+            }),
+            "ud" => Some(AnnotationScheme {
+                pp_analysis: PpAnalysis::ContentHead,
+                relations: Relations {
+                    pp: strings(&["obl", "nmod"]),
+                    prep_complement: Vec::new(),
+                    auxiliary: Vec::new(),
+                    auxiliary_dependent: strings(&["aux"]),
+                    verb_complement: Vec::new(),
+                    case: strings(&["case"]),
+                    fixed: strings(&["fixed"]),
+                    arguments: strings(&["nsubj", "csubj", "obj", "iobj", "ccomp", "xcomp", "obl"]),
+                    excluded: strings(&[
+                        "nmod:poss",
+                        "nmod:tmod",
+                        "nmod:npmod",
+                        "obl:tmod",
+                        "obl:npmod",
+                    ]),
+                },
+                // Universal part-of-speech tags do not distinguish finite
+                // verbs, relative pronouns, or interrogatives.
+                tags: Tags {
+                    coarse: true,
+                    noun_prefixes: strings(&["NOUN", "PROPN"]),
+                    verb_prefixes: strings(&["VERB", "AUX"]),
+                    finite_verbs: Vec::new(),
+                    nonfinite_verbs: Vec::new(),
+                    prepositions: strings(&["ADP"]),
+                    complementizers: strings(&["SCONJ"]),
+                    relatives: Vec::new(),
+                    interrogatives: Vec::new(),
+                    coordinations: strings(&["CCONJ"]),
+                    verb_particles: Vec::new(),
+                    punctuation_prefixes: strings(&["PUNCT"]),
+                },
+                ..AnnotationScheme::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Read an annotation scheme from a TOML file.
+    pub fn from_file<P>(path: P) -> Result<AnnotationScheme>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", path.to_string_lossy(), e))
+        })?;
+        AnnotationScheme::from_toml(&data)
+    }
+
+    /// Parse an annotation scheme from TOML.
+    pub fn from_toml(data: &str) -> Result<AnnotationScheme> {
+        toml::from_str(data).map_err(Error::Scheme)
+    }
+
+    /// Get a built-in annotation scheme, or read the scheme from a file
+    /// if there is no preset with the given name.
+    pub fn preset_or_file(name: &str) -> Result<AnnotationScheme> {
+        match AnnotationScheme::preset(name) {
+            Some(scheme) => Ok(scheme),
+            None => AnnotationScheme::from_file(name),
+        }
+    }
+
+    /// Get the part-of-speech tag of a token that the tag predicates of
+    /// this scheme apply to.
+    pub fn tag<'a>(&self, token: &'a Token) -> Option<&'a str> {
+        if self.tags.coarse {
+            token.cpos()
+        } else {
+            token.pos()
+        }
+    }
+
+    pub fn is_pp_relation(&self, relation: &str) -> bool {
+        self.matches_relation(&self.relations.pp, relation)
+    }
+
+    pub fn is_prep_complement_relation(&self, relation: &str) -> bool {
+        self.matches_relation(&self.relations.prep_complement, relation)
+    }
+
+    pub fn is_auxiliary_relation(&self, relation: &str) -> bool {
+        self.matches_relation(&self.relations.auxiliary, relation)
+    }
+
+    pub fn is_auxiliary_dependent_relation(&self, relation: &str) -> bool {
+        self.matches_relation(&self.relations.auxiliary_dependent, relation)
+    }
+
+    /// Returns `true` if the relation connects two verbs of a verb chain:
+    /// a verb to the verb that governs it or an auxiliary to the verb
+    /// that it governs. Verb complement relations are only verb chain
+    /// relations if the dependent has a non-finite verb tag.
+    pub fn is_verb_chain_relation(&self, relation: &str, dependent_tag: Option<&str>) -> bool {
+        if self.is_auxiliary_relation(relation) || self.is_auxiliary_dependent_relation(relation) {
+            return true;
+        }
+
+        match dependent_tag {
+            Some(tag) => {
+                self.matches_relation(&self.relations.verb_complement, relation) &&
+                    self.is_nonfinite_verb_tag(tag)
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_case_relation(&self, relation: &str) -> bool {
+        self.matches_relation(&self.relations.case, relation)
+    }
+
+    pub fn is_fixed_relation(&self, relation: &str) -> bool {
+        self.matches_relation(&self.relations.fixed, relation)
+    }
+
+    pub fn is_argument_relation(&self, relation: &str) -> bool {
+        self.matches_relation(&self.relations.arguments, relation)
+    }
+
+    fn matches_relation(&self, relations: &[String], relation: &str) -> bool {
+        contains(relations, relation) ||
+            (!contains(&self.relations.excluded, relation) && contains_relation(relations, relation))
+    }
+
+    pub fn is_noun_tag(&self, tag: &str) -> bool {
+        self.tags.noun_prefixes.iter().any(|p| tag.starts_with(p.as_str()))
+    }
+
+    pub fn is_verb_tag(&self, tag: &str) -> bool {
+        self.tags.verb_prefixes.iter().any(|p| tag.starts_with(p.as_str()))
+    }
+
+    pub fn is_finite_verb_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.finite_verbs, tag)
+    }
+
+    pub fn is_nonfinite_verb_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.nonfinite_verbs, tag)
+    }
+
+    pub fn is_preposition_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.prepositions, tag)
+    }
+
+    pub fn is_complementizer_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.complementizers, tag)
+    }
+
+    pub fn is_relative_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.relatives, tag)
+    }
+
+    pub fn is_interrogative_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.interrogatives, tag)
+    }
+
+    pub fn is_coordination_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.coordinations, tag)
+    }
+
+    pub fn is_verb_particle_tag(&self, tag: &str) -> bool {
+        contains(&self.tags.verb_particles, tag)
+    }
+
+    pub fn is_punctuation_tag(&self, tag: &str) -> bool {
+        self.tags.punctuation_prefixes.iter().any(|p| tag.starts_with(p.as_str()))
+    }
+}
+
+fn contains(values: &[String], value: &str) -> bool {
+    values.iter().any(|v| v == value)
+}
+
+pub(crate) fn contains_relation(relations: &[String], relation: &str) -> bool {
+    contains(relations, relation)
+        || relation
+            .find(':')
+            .map(|colon| contains(relations, &relation[..colon]))
+            .unwrap_or(false)
+}
+
+pub(crate) fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|&v| v.to_owned()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnnotationScheme;
+    use test_util::sentence;
+
+    #[test]
+    fn excluded_subtypes() {
+        let scheme = AnnotationScheme::preset("ud").unwrap();
+        assert!(scheme.is_pp_relation("nmod"));
+        assert!(scheme.is_pp_relation("obl:arg"));
+        assert!(!scheme.is_pp_relation("nmod:poss"));
+        assert!(!scheme.is_pp_relation("obl:tmod"));
+
+        let scheme = AnnotationScheme::from_toml(
+            "[relations]\n\
+             pp = [\"nmod\", \"nmod:poss\"]\n\
+             excluded = [\"nmod:poss\"]",
+        ).unwrap();
+        assert!(scheme.is_pp_relation("nmod:poss"));
+    }
+
+    #[test]
+    fn coarse_tags() {
+        let sentence = sentence(
+            "1  Peter  Peter  PROPN  NE    _  2  nsubj
+             2  kommt  kommen VERB   VVFIN _  0  root",
+        );
+
+        let scheme = AnnotationScheme::preset("ud").unwrap();
+        assert_eq!(scheme.tag(&sentence[0]), Some("PROPN"));
+        assert!(scheme.is_noun_tag("PROPN"));
+        assert!(!scheme.is_noun_tag("NUM"));
+
+        let scheme = AnnotationScheme::default();
+        assert_eq!(scheme.tag(&sentence[1]), Some("VVFIN"));
+    }
+}
@@ -0,0 +1,17 @@
+use std::io::BufReader;
+
+use conllx::{ReadSentence, Reader, Sentence};
+
+/// Read a sentence from CoNLL-X data, where columns may be separated by
+/// any amount of whitespace.
+pub fn sentence(data: &str) -> Sentence {
+    let data: Vec<_> = data
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join("\t"))
+        .collect();
+
+    Reader::new(BufReader::new(data.join("\n").as_bytes()))
+        .read_sentence()
+        .expect("cannot read sentence")
+        .expect("no sentence")
+}
@@ -0,0 +1,382 @@
+use std::fmt;
+use std::str::FromStr;
+
+use conllx::{Features, Token};
+use petgraph::graph::NodeIndex;
+
+use clause::{segment_clauses, Clauses};
+use graph::{DependencyGraph, DependencyNode};
+use scheme::AnnotationScheme;
+
+/// Topological fields of German clauses (TüBa-D/Z inventory).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TopologicalField {
+    /// Initial field (Vorfeld).
+    VF,
+
+    /// Left bracket (linke Klammer).
+    LK,
+
+    /// Middle field (Mittelfeld).
+    MF,
+
+    /// Verb complex, the right bracket.
+    VC,
+
+    /// Final field (Nachfeld).
+    NF,
+
+    /// Complementizer field.
+    C,
+
+    /// Field of coordinating conjunctions.
+    KOORD,
+
+    /// Left dislocation (Linksversetzung).
+    LV,
+
+    /// Field of paratactic conjunctions, such as _denn_.
+    PARORD,
+
+    /// Unknown field.
+    UK,
+}
+
+impl TopologicalField {
+    /// All topological fields.
+    pub fn all() -> &'static [TopologicalField] {
+        &[
+            TopologicalField::VF,
+            TopologicalField::LK,
+            TopologicalField::MF,
+            TopologicalField::VC,
+            TopologicalField::NF,
+            TopologicalField::C,
+            TopologicalField::KOORD,
+            TopologicalField::LV,
+            TopologicalField::PARORD,
+            TopologicalField::UK,
+        ]
+    }
+
+    /// The TüBa-D/Z name of the field.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TopologicalField::VF => "VF",
+            TopologicalField::LK => "LK",
+            TopologicalField::MF => "MF",
+            TopologicalField::VC => "VC",
+            TopologicalField::NF => "NF",
+            TopologicalField::C => "C",
+            TopologicalField::KOORD => "KOORD",
+            TopologicalField::LV => "LV",
+            TopologicalField::PARORD => "PARORD",
+            TopologicalField::UK => "UK",
+        }
+    }
+
+    /// The label of the field in an annotation scheme.
+    pub fn label<'a>(&self, scheme: &'a AnnotationScheme) -> &'a str {
+        let fields = &scheme.topological_fields;
+        match *self {
+            TopologicalField::VF => &fields.vf,
+            TopologicalField::LK => &fields.lk,
+            TopologicalField::MF => &fields.mf,
+            TopologicalField::VC => &fields.vc,
+            TopologicalField::NF => &fields.nf,
+            TopologicalField::C => &fields.c,
+            TopologicalField::KOORD => &fields.koord,
+            TopologicalField::LV => &fields.lv,
+            TopologicalField::PARORD => &fields.parord,
+            TopologicalField::UK => &fields.unknown,
+        }
+    }
+
+    /// Get the field with the given label in an annotation scheme.
+    pub fn from_label(scheme: &AnnotationScheme, label: &str) -> Option<TopologicalField> {
+        TopologicalField::all()
+            .iter()
+            .cloned()
+            .find(|field| field.label(scheme) == label)
+    }
+}
+
+impl fmt::Display for TopologicalField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for TopologicalField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TopologicalField::all()
+            .iter()
+            .cloned()
+            .find(|field| field.name() == s)
+            .ok_or_else(|| format!("unknown topological field: {}", s))
+    }
+}
+
+impl<'a> DependencyNode<'a> {
+    /// The topological field of the token.
+    ///
+    /// Returns `None` if the token does not have a field feature or if the
+    /// field is not known in the annotation scheme.
+    pub fn topological_field(&self, scheme: &AnnotationScheme) -> Option<TopologicalField> {
+        feature_value(self.token, &scheme.topological_fields.feature)
+            .and_then(|label| TopologicalField::from_label(scheme, &label))
+    }
+}
+
+/// Get the value of a token feature.
+pub fn feature_value(token: &Token, feature: &str) -> Option<String> {
+    token
+        .features()
+        .map(Features::as_map)
+        .and_then(|f| f.get(feature))
+        .and_then(|v| v.clone())
+}
+
+/// Set the value of a token feature, replacing its current value.
+pub fn set_feature_value(token: &mut Token, feature: &str, value: &str) {
+    let feature_value = format!("{}:{}", feature, value);
+
+    let mut features: Vec<_> = token
+        .features()
+        .map(|f| f.as_str().split('|').map(ToOwned::to_owned).collect())
+        .unwrap_or_default();
+
+    match features
+        .iter_mut()
+        .find(|fv| fv.split(':').next() == Some(feature))
+    {
+        Some(fv) => *fv = feature_value,
+        None => features.push(feature_value),
+    }
+
+    token.set_features(Some(Features::from_string(features.join("|"))));
+}
+
+/// A contiguous sequence of tokens in the same topological field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FieldSpan {
+    pub field: TopologicalField,
+
+    /// Offset of the first token.
+    pub start: usize,
+
+    /// Offset after the last token.
+    pub end: usize,
+}
+
+/// Get the spans of tokens in the same topological field, in sentence
+/// order. Tokens without a field are not part of a span.
+///
+/// Spans end at clause boundaries (see `segment_clauses`), so that
+/// adjacent fields of the same type in different clauses, such as the
+/// verb complexes of an embedded and a main clause, are separate spans.
+pub fn field_spans<'a>(
+    graph: &'a DependencyGraph<'a>,
+    scheme: &'a AnnotationScheme,
+) -> FieldSpans<'a> {
+    let len = graph
+        .node_indices()
+        .filter(|&idx| !graph[idx].is_root())
+        .count();
+
+    FieldSpans {
+        graph,
+        scheme,
+        clauses: segment_clauses(graph, scheme),
+        offset: 0,
+        len,
+    }
+}
+
+pub struct FieldSpans<'a> {
+    graph: &'a DependencyGraph<'a>,
+    scheme: &'a AnnotationScheme,
+    clauses: Clauses,
+    offset: usize,
+    len: usize,
+}
+
+impl<'a> FieldSpans<'a> {
+    fn field(&self, offset: usize) -> Option<TopologicalField> {
+        // Node indices are token offsets.
+        self.graph[NodeIndex::new(offset)].topological_field(self.scheme)
+    }
+
+    fn clause(&self, offset: usize) -> Option<usize> {
+        self.clauses.clause_of(NodeIndex::new(offset))
+    }
+}
+
+impl<'a> Iterator for FieldSpans<'a> {
+    type Item = FieldSpan;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.len {
+            let start = self.offset;
+            self.offset += 1;
+
+            let field = match self.field(start) {
+                Some(field) => field,
+                None => continue,
+            };
+
+            let clause = self.clause(start);
+            while self.offset < self.len &&
+                self.field(self.offset) == Some(field) &&
+                self.clause(self.offset) == clause
+            {
+                self.offset += 1;
+            }
+
+            return Some(FieldSpan {
+                field,
+                start,
+                end: self.offset,
+            });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use conllx::{Features, Token};
+    use petgraph::graph::NodeIndex;
+
+    use super::{feature_value, field_spans, set_feature_value, FieldSpan, TopologicalField};
+    use graph::sentence_to_graph;
+    use scheme::AnnotationScheme;
+    use test_util::sentence;
+
+    #[test]
+    fn labels() {
+        let scheme = AnnotationScheme::default();
+
+        for &field in TopologicalField::all() {
+            assert_eq!(TopologicalField::from_label(&scheme, field.label(&scheme)), Some(field));
+            assert_eq!(field.name().parse(), Ok(field));
+        }
+
+        assert_eq!(TopologicalField::from_label(&scheme, "XX"), None);
+        assert!("XX".parse::<TopologicalField>().is_err());
+
+        let mut scheme = AnnotationScheme::default();
+        scheme.topological_fields.mf = "Mittelfeld".to_owned();
+        assert_eq!(TopologicalField::MF.label(&scheme), "Mittelfeld");
+        assert_eq!(
+            TopologicalField::from_label(&scheme, "Mittelfeld"),
+            Some(TopologicalField::MF)
+        );
+        assert_eq!(TopologicalField::from_label(&scheme, "MF"), None);
+    }
+
+    #[test]
+    fn set_feature_values() {
+        let mut token = Token::new();
+        set_feature_value(&mut token, "tf", "MF");
+        assert_eq!(token.features().map(Features::as_str), Some("tf:MF"));
+
+        token.set_features(Some(Features::from_string("case:dat|tf:VF|number:sg")));
+
+        // Existing values are replaced in place.
+        set_feature_value(&mut token, "tf", "NF");
+        assert_eq!(
+            token.features().map(Features::as_str),
+            Some("case:dat|tf:NF|number:sg")
+        );
+
+        // New features are appended.
+        set_feature_value(&mut token, "gender", "neut");
+        assert_eq!(
+            token.features().map(Features::as_str),
+            Some("case:dat|tf:NF|number:sg|gender:neut")
+        );
+
+        assert_eq!(feature_value(&token, "tf"), Some("NF".to_owned()));
+        assert_eq!(feature_value(&token, "person"), None);
+        assert_eq!(feature_value(&Token::new(), "tf"), None);
+    }
+
+    #[test]
+    fn spans_of_a_clause() {
+        let sentence = sentence(
+            "1  Er       er      PPER   PPER   tf:VF  2  SUBJ     _  _
+             2  hat      haben   VAFIN  VAFIN  tf:LK  0  ROOT     _  _
+             3  ein      ein     ART    ART    tf:MF  4  DET      _  _
+             4  Buch     Buch    NN     NN     tf:MF  5  OBJA     _  _
+             5  gelesen  lesen   VVPP   VVPP   tf:VC  2  AUX      _  _
+             6  .        .       $.     $.     _      5  -PUNCT-  _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, true).unwrap();
+        let scheme = AnnotationScheme::default();
+
+        let node = &graph[NodeIndex::new(2)];
+        assert_eq!(node.topological_field(&scheme), Some(TopologicalField::MF));
+
+        let spans: Vec<_> = field_spans(&graph, &scheme).collect();
+        assert_eq!(
+            spans,
+            vec![
+                FieldSpan {
+                    field: TopologicalField::VF,
+                    start: 0,
+                    end: 1,
+                },
+                FieldSpan {
+                    field: TopologicalField::LK,
+                    start: 1,
+                    end: 2,
+                },
+                FieldSpan {
+                    field: TopologicalField::MF,
+                    start: 2,
+                    end: 4,
+                },
+                FieldSpan {
+                    field: TopologicalField::VC,
+                    start: 4,
+                    end: 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_end_at_clause_boundaries() {
+        // The verb complex of the embedded clause is followed by the
+        // verb complex of the main clause.
+        let sentence = sentence(
+            "1  Er        er         PPER   PPER   tf:VF  2  SUBJ  _  _
+             2  hat       haben      VAFIN  VAFIN  tf:LK  0  ROOT  _  _
+             3  was       was        PWS    PWS    tf:C   5  OBJA  _  _
+             4  er        er         PPER   PPER   tf:MF  5  SUBJ  _  _
+             5  wollte    wollen     VMFIN  VMFIN  tf:VC  6  OBJA  _  _
+             6  bekommen  bekommen   VVPP   VVPP   tf:VC  2  AUX   _  _",
+        );
+        let graph = sentence_to_graph(&sentence, false, false).unwrap();
+        let scheme = AnnotationScheme::default();
+
+        let spans: Vec<_> = field_spans(&graph, &scheme)
+            .map(|span| (span.field, span.start, span.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (TopologicalField::VF, 0, 1),
+                (TopologicalField::LK, 1, 2),
+                (TopologicalField::C, 2, 3),
+                (TopologicalField::MF, 3, 4),
+                (TopologicalField::VC, 4, 5),
+                (TopologicalField::VC, 5, 6),
+            ]
+        );
+    }
+}
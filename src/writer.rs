@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use conllx;
+use conllx::{Sentence, WriteSentence};
+
+/// A writer for CoNLL-X sentences.
+///
+/// Unlike `conllx::Writer`, this writer writes the projective head
+/// relation in the PDEPREL column and ends every sentence with an empty
+/// line.
+pub struct ConllxWriter<W> {
+    write: W,
+}
+
+impl<W: Write> ConllxWriter<W> {
+    pub fn new(write: W) -> Self {
+        ConllxWriter { write }
+    }
+}
+
+impl<W: Write> WriteSentence for ConllxWriter<W> {
+    fn write_sentence(&mut self, sentence: &Sentence) -> conllx::Result<()> {
+        for (idx, token) in sentence.iter().enumerate() {
+            writeln!(
+                self.write,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                idx + 1,
+                token.form().unwrap_or("_"),
+                token.lemma().unwrap_or("_"),
+                token.cpos().unwrap_or("_"),
+                token.pos().unwrap_or("_"),
+                token.features().map(|f| f.as_str()).unwrap_or("_"),
+                column(token.head()),
+                token.head_rel().unwrap_or("_"),
+                column(token.p_head()),
+                token.p_head_rel().unwrap_or("_")
+            )?;
+        }
+
+        writeln!(self.write)?;
+
+        Ok(())
+    }
+}
+
+fn column(value: Option<usize>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "_".to_owned())
+}